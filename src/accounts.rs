@@ -0,0 +1,285 @@
+use crate::game::PublicKey;
+use astria_core::primitive::v1::Address;
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const BALANCE_KEY_PREFIX: &[u8] = b"balance/";
+const WITHDRAWAL_KEY_PREFIX: &[u8] = b"withdrawal/";
+const NONCE_KEY_PREFIX: &[u8] = b"nonce/";
+const NEXT_WITHDRAWAL_ID_KEY: &[u8] = b"meta/next_withdrawal_id";
+// Longest memo carried by a withdrawal, in bytes
+pub const MAX_MEMO_LEN: usize = 256;
+// Marks deposits among the inputs folded into the transactions root
+const DEPOSIT_TAG: u8 = 0xff;
+
+// Per-account, per-asset balances. Accounts are keyed by the hex encoded public key so the
+// state serializes to JSON and iterates in key order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Accounts {
+    balances: BTreeMap<String, BTreeMap<String, u128>>,
+    withdrawals: BTreeMap<u64, Withdrawal>, // Withdrawal events by id, in execution order
+    next_withdrawal_id: u64,
+    nonces: BTreeMap<String, u64>, // Next nonce of every account that has signed a transaction
+}
+
+// Funds debited on the rollup to be released on the sequencer by the bridge withdrawer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub id: u64,
+    pub account: PublicKey, // Rollup account that was debited
+    pub asset: String,
+    pub amount: u128,
+    pub destination: String, // Sequencer address receiving the funds
+    pub memo: String,
+    pub height: u32, // Block that executed the withdrawal
+}
+
+// Funds bridged in from the sequencer, credited when the block containing them executes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deposit {
+    pub destination: String, // hex encoded public key of the rollup account
+    pub asset: String,       // denomination of the bridged asset
+    pub amount: u128,
+    pub source_transaction_id: String, // Sequencer transaction that carried the deposit
+    pub source_action_index: u64,      // Position of the deposit among its actions
+}
+
+impl Accounts {
+    // Balance of an account in a single asset, zero if it never held any
+    pub fn balance(&self, account: &PublicKey, asset: &str) -> u128 {
+        self.balances
+            .get(&hex::encode(account))
+            .and_then(|assets| assets.get(asset))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Every non-zero balance of an account, by asset
+    pub fn balances(&self, account: &PublicKey) -> BTreeMap<String, u128> {
+        self.balances
+            .get(&hex::encode(account))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn credit(&mut self, account: &PublicKey, asset: &str, amount: u128) -> Result<(), String> {
+        let balance = self
+            .balances
+            .entry(hex::encode(account))
+            .or_default()
+            .entry(asset.to_string())
+            .or_insert(0);
+        *balance = balance.checked_add(amount).ok_or("Balance overflow")?;
+        Ok(())
+    }
+
+    pub fn debit(&mut self, account: &PublicKey, asset: &str, amount: u128) -> Result<(), String> {
+        let key = hex::encode(account);
+        let balance = self.balance(account, asset);
+        if balance < amount {
+            return Err(format!(
+                "Insufficient {} balance: have {}, need {}",
+                asset, balance, amount
+            ));
+        }
+        let assets = self.balances.entry(key.clone()).or_default();
+        if balance == amount {
+            // Drop empty entries so they don't linger in the state root
+            assets.remove(asset);
+            if assets.is_empty() {
+                self.balances.remove(&key);
+            }
+        } else {
+            assets.insert(asset.to_string(), balance - amount);
+        }
+        Ok(())
+    }
+
+    // Debit an account and record a withdrawal event, returning the event id
+    pub fn withdraw(
+        &mut self,
+        account: &PublicKey,
+        asset: &str,
+        amount: u128,
+        destination: &str,
+        memo: &str,
+        height: u32,
+    ) -> Result<u64, String> {
+        if amount == 0 {
+            return Err("Withdrawal amount must be positive".into());
+        }
+        if memo.len() > MAX_MEMO_LEN {
+            return Err(format!("Memo longer than {} bytes", MAX_MEMO_LEN));
+        }
+        destination
+            .parse::<Address>()
+            .map_err(|e| format!("Invalid sequencer address {}: {}", destination, e))?;
+        self.debit(account, asset, amount)?;
+        let id = self.next_withdrawal_id;
+        self.next_withdrawal_id += 1;
+        self.withdrawals.insert(
+            id,
+            Withdrawal {
+                id,
+                account: *account,
+                asset: asset.to_string(),
+                amount,
+                destination: destination.to_string(),
+                memo: memo.to_string(),
+                height,
+            },
+        );
+        Ok(id)
+    }
+
+    // Withdrawal events executed in blocks `from_height..=to_height`, oldest first
+    pub fn withdrawals(&self, from_height: u32, to_height: u32) -> Vec<&Withdrawal> {
+        self.withdrawals
+            .values()
+            .filter(|withdrawal| (from_height..=to_height).contains(&withdrawal.height))
+            .collect()
+    }
+
+    // Nonce the account's next transaction must carry
+    pub fn next_nonce(&self, account: &PublicKey) -> u64 {
+        self.nonces.get(&hex::encode(account)).copied().unwrap_or(0)
+    }
+
+    // Reject a transaction whose nonce is not the account's next one
+    pub fn check_nonce(&self, account: &PublicKey, nonce: u64) -> Result<(), String> {
+        let expected = self.next_nonce(account);
+        if nonce != expected {
+            return Err(format!(
+                "Invalid nonce: expected {}, got {}",
+                expected, nonce
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn bump_nonce(&mut self, account: &PublicKey) {
+        *self.nonces.entry(hex::encode(account)).or_insert(0) += 1;
+    }
+
+    // Credit a deposit to its destination account, or to `holding_account` if the destination
+    // is not a rollup account. Returns the holding account if it was credited. Deposits
+    // missing their source or amount are malformed and rejected.
+    pub fn apply_deposit(
+        &mut self,
+        deposit: &Deposit,
+        holding_account: Option<PublicKey>,
+    ) -> Result<Option<PublicKey>, String> {
+        if deposit.source_transaction_id.is_empty() {
+            return Err("Deposit without a source transaction".into());
+        }
+        if deposit.amount == 0 {
+            return Err("Deposit amount must be positive".into());
+        }
+        let mut account: PublicKey = [0u8; 32];
+        let held = match hex::decode_to_slice(
+            deposit.destination.trim_start_matches("0x"),
+            &mut account,
+        ) {
+            Ok(()) => None,
+            Err(e) => {
+                account = holding_account.ok_or_else(|| {
+                    format!("Invalid deposit destination {}: {}", deposit.destination, e)
+                })?;
+                Some(account)
+            }
+        };
+        self.credit(&account, &deposit.asset, deposit.amount)?;
+        Ok(held)
+    }
+
+    // Key/value pairs committed to by the state root: balance/{account}/{asset} -> amount
+    pub fn state_leaves(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut leaves = Vec::new();
+        for (account, assets) in &self.balances {
+            for (asset, amount) in assets {
+                let mut key = BALANCE_KEY_PREFIX.to_vec();
+                key.extend_from_slice(account.as_bytes());
+                key.push(b'/');
+                key.extend_from_slice(asset.as_bytes());
+                leaves.push((key, amount.to_be_bytes().to_vec()));
+            }
+        }
+        for (account, nonce) in &self.nonces {
+            let mut key = NONCE_KEY_PREFIX.to_vec();
+            key.extend_from_slice(account.as_bytes());
+            leaves.push((key, nonce.to_be_bytes().to_vec()));
+        }
+        for (id, withdrawal) in &self.withdrawals {
+            let mut key = WITHDRAWAL_KEY_PREFIX.to_vec();
+            key.extend_from_slice(&id.to_be_bytes());
+            leaves.push((
+                key,
+                serde_json::to_vec(withdrawal).expect("withdrawal serializes to json"),
+            ));
+        }
+        // Committed so a restored state cannot reuse the id of a pending withdrawal
+        leaves.push((
+            NEXT_WITHDRAWAL_ID_KEY.to_vec(),
+            self.next_withdrawal_id.to_be_bytes().to_vec(),
+        ));
+        leaves
+    }
+}
+
+impl Deposit {
+    // Bytes folded into the transactions root when the deposit is applied. The sequencer
+    // source makes them, and so the deposit's hash, unique.
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::new();
+        buffer.put_u8(DEPOSIT_TAG);
+        put_string(&mut buffer, &self.source_transaction_id);
+        buffer.put_u64(self.source_action_index);
+        put_string(&mut buffer, &self.destination);
+        put_string(&mut buffer, &self.asset);
+        buffer.put_u128(self.amount);
+        buffer.freeze()
+    }
+}
+
+fn put_string(buffer: &mut BytesMut, value: &str) {
+    buffer.put_u32(value.len() as u32);
+    buffer.put_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_next_withdrawal_id_is_committed() {
+        let mut accounts = Accounts::default();
+        accounts.next_withdrawal_id = 1;
+        let leaves = accounts.state_leaves();
+        assert!(leaves.contains(&(NEXT_WITHDRAWAL_ID_KEY.to_vec(), 1u64.to_be_bytes().to_vec())));
+        // A state that would hand out an existing id again has different leaves
+        let mut tampered = accounts.clone();
+        tampered.next_withdrawal_id = 0;
+        assert_ne!(tampered.state_leaves(), leaves);
+    }
+
+    fn deposit(amount: u128, source_transaction_id: &str) -> Deposit {
+        Deposit {
+            destination: hex::encode([1u8; 32]),
+            asset: "nria".into(),
+            amount,
+            source_transaction_id: source_transaction_id.into(),
+            source_action_index: 0,
+        }
+    }
+
+    #[test]
+    fn malformed_deposits_are_rejected() {
+        let mut accounts = Accounts::default();
+        assert!(accounts.apply_deposit(&deposit(0, "ab"), None).is_err());
+        assert!(accounts.apply_deposit(&deposit(5, ""), None).is_err());
+        assert_eq!(accounts.balance(&[1u8; 32], "nria"), 0);
+        assert_eq!(accounts.apply_deposit(&deposit(5, "ab"), None), Ok(None));
+        assert_eq!(accounts.balance(&[1u8; 32], "nria"), 5);
+    }
+}
@@ -0,0 +1,293 @@
+use astria_core::generated::execution::v1 as execution;
+use bytes::Bytes;
+use chess_rollup::block_log::{self, Call, LogEntry};
+use chess_rollup::chess;
+use chess_rollup::config::Config;
+use chess_rollup::execution_service::{self, BlockExecution};
+use chess_rollup::game::GameManager;
+use chess_rollup::genesis::Genesis;
+use chess_rollup::rollup_app::AppState;
+use color_eyre::eyre::{self, eyre};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Re-executes recorded blocks from the genesis file named by the environment, like the node's
+// own, and checks that every block hash matches.
+const USAGE: &str = "usage: replay <file> [--requests] [--dump-state <file>]
+
+  <file>               recorded execution log, or with --requests a length-delimited
+                       export of ExecuteBlockRequests
+  --requests           read an export of requests instead of a recorded log; each block's
+                       parent is taken as firm
+  --dump-state <file>  write the final state as JSON";
+
+struct Args {
+    input: PathBuf,
+    requests: bool,
+    dump_state: Option<PathBuf>,
+}
+
+// One step of the replay, with what the recording node answered
+enum Step {
+    Execute {
+        request: execution::ExecuteBlockRequest,
+        expected: Expected,
+    },
+    Commit(execution::CommitmentState),
+}
+
+enum Expected {
+    Hash(Bytes),
+    Failure(String),
+    Unknown, // Exports carry no responses
+}
+
+// Final state written by --dump-state
+#[derive(Serialize)]
+struct StateDump<'a> {
+    height: u32,
+    hash: String,
+    firm_height: u32,
+    state_root: String,
+    state: &'a GameManager,
+}
+
+fn main() -> eyre::Result<()> {
+    let args = parse_args()?;
+    let config = Config::from_env()?;
+    let steps = if args.requests {
+        block_log::read_messages::<execution::ExecuteBlockRequest>(&args.input)?
+            .into_iter()
+            .map(|request| Step::Execute {
+                request,
+                expected: Expected::Unknown,
+            })
+            .collect()
+    } else {
+        block_log::read_messages::<LogEntry>(&args.input)?
+            .into_iter()
+            .filter_map(log_step)
+            .collect::<Vec<_>>()
+    };
+
+    let genesis = Genesis::load(&config.genesis_filepath)?;
+    let (mut state, _) = chess::genesis_state(&genesis)?;
+    let result = replay(&mut state, &steps, args.requests);
+    if let Some(path) = &args.dump_state {
+        dump_state(&state, path)?;
+    }
+    result
+}
+
+fn parse_args() -> eyre::Result<Args> {
+    let mut input = None;
+    let mut requests = false;
+    let mut dump_state = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--requests" => requests = true,
+            "--dump-state" => {
+                dump_state = Some(PathBuf::from(args.next().ok_or_else(|| eyre!(USAGE))?));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ if input.is_none() && !arg.starts_with("--") => input = Some(PathBuf::from(arg)),
+            _ => return Err(eyre!("unexpected argument {}\n{}", arg, USAGE)),
+        }
+    }
+    Ok(Args {
+        input: input.ok_or_else(|| eyre!(USAGE))?,
+        requests,
+        dump_state,
+    })
+}
+
+// Steps that changed the recording node's state; genesis info and failed commitments did not
+fn log_step(entry: LogEntry) -> Option<Step> {
+    match entry.call? {
+        Call::ExecuteBlock(call) => Some(Step::Execute {
+            request: call.request?,
+            expected: match call.response {
+                Some(block) => Expected::Hash(block.hash),
+                None => Expected::Failure(call.error),
+            },
+        }),
+        Call::UpdateCommitmentState(call) => {
+            call.response?;
+            Some(Step::Commit(call.request?.commitment_state?))
+        }
+        Call::GetGenesisInfo(_) => None,
+    }
+}
+
+// Without recorded commitments nothing moves the firm height, so `advance_firm` makes each
+// block's parent firm to drop older game states as the replay goes
+fn replay(state: &mut AppState, steps: &[Step], advance_firm: bool) -> eyre::Result<()> {
+    // Game state before and after the last executed block, to explain a divergence that only
+    // shows when a later request builds on a hash we did not produce
+    let mut last: Option<(GameManager, BlockExecution)> = None;
+    let mut executed = 0;
+    for (index, step) in steps.iter().enumerate() {
+        let (request, expected) = match step {
+            Step::Commit(commitment) => {
+                execution_service::apply_commitment(state, commitment).map_err(|status| {
+                    eyre!("step {}: commitment rejected: {}", index, status.message())
+                })?;
+                continue;
+            }
+            Step::Execute { request, expected } => (request, expected),
+        };
+        let parent = state
+            .get_block_by_hash(&request.prev_block_hash)
+            .and_then(|parent| state.get_game_state(parent.number()))
+            .cloned();
+        let execution = match (
+            execution_service::execute_request(state, request.clone()),
+            expected,
+        ) {
+            (Ok(execution), Expected::Hash(hash)) if execution.block.hash != hash => {
+                report_divergence(parent.as_ref(), &execution, Some(hash.clone()));
+                return Err(eyre!("replay diverged at block {}", execution.height()));
+            }
+            (Ok(execution), Expected::Failure(error)) => {
+                report_divergence(parent.as_ref(), &execution, None);
+                return Err(eyre!(
+                    "block {} was rejected by the recording node: {}",
+                    execution.height(),
+                    error
+                ));
+            }
+            (Ok(execution), _) => execution,
+            (Err(_), Expected::Failure(_)) => continue,
+            (Err(status), _) => {
+                if let Some((parent, execution)) = &last {
+                    println!(
+                        "step {} builds on {}, which this replay did not produce; the last \
+                         block replayed was:",
+                        index,
+                        hex::encode(&request.prev_block_hash)
+                    );
+                    report_divergence(Some(parent), execution, None);
+                }
+                return Err(eyre!("step {}: {}", index, status.message()));
+            }
+        };
+        let height = execution.height();
+        execution.clone().record(state);
+        if advance_firm {
+            state.firm_height = height - 1;
+            state.prune_game_states();
+        }
+        executed += 1;
+        last = parent.map(|parent| (parent, execution));
+    }
+    println!(
+        "replayed {} blocks, soft height {}, firm height {}",
+        executed, state.soft_height, state.firm_height
+    );
+    Ok(())
+}
+
+// Print the produced block and the state changes it made
+fn report_divergence(
+    parent: Option<&GameManager>,
+    execution: &BlockExecution,
+    recorded: Option<Bytes>,
+) {
+    println!("block {}", execution.height());
+    println!(
+        "  produced hash      {}",
+        hex::encode(&execution.block.hash)
+    );
+    if let Some(recorded) = recorded {
+        println!("  recorded hash      {}", hex::encode(recorded));
+    }
+    println!(
+        "  parent hash        {}",
+        hex::encode(&execution.block.parent_block_hash)
+    );
+    println!(
+        "  transactions root  {}",
+        hex::encode(execution.executed.roots.transactions_root)
+    );
+    println!(
+        "  state root         {}",
+        hex::encode(execution.executed.roots.state_root)
+    );
+    for receipt in &execution.executed.body.receipts {
+        if let Some(error) = &receipt.error {
+            println!(
+                "  rejected {} {}: {}",
+                receipt.index,
+                hex::encode(receipt.tx_hash),
+                error
+            );
+        }
+    }
+    let Some(parent) = parent else {
+        return;
+    };
+    println!("state changes:");
+    let before: BTreeMap<Vec<u8>, Vec<u8>> = parent.state_leaves().into_iter().collect();
+    let after: BTreeMap<Vec<u8>, Vec<u8>> =
+        execution.game_manager.state_leaves().into_iter().collect();
+    for (key, value) in &after {
+        match before.get(key) {
+            None => println!("  + {} = {}", display_key(key), display_bytes(value)),
+            Some(old) if old != value => println!(
+                "  ~ {}: {} -> {}",
+                display_key(key),
+                display_bytes(old),
+                display_bytes(value)
+            ),
+            Some(_) => {}
+        }
+    }
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        println!("  - {}", display_key(key));
+    }
+}
+
+// State keys are a readable prefix followed by an account, asset or big-endian id
+fn display_key(key: &[u8]) -> String {
+    match key.iter().position(|byte| *byte == b'/') {
+        Some(split) => format!(
+            "{}/{}",
+            String::from_utf8_lossy(&key[..split]),
+            display_bytes(&key[split + 1..])
+        ),
+        None => display_bytes(key),
+    }
+}
+
+fn display_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(char::is_control) => text.to_string(),
+        _ => hex::encode(bytes),
+    }
+}
+
+fn dump_state(state: &AppState, path: &Path) -> eyre::Result<()> {
+    let height = state.soft_height;
+    let game_manager = state
+        .get_game_state(height)
+        .ok_or_else(|| eyre!("no game state kept for block {}", height))?;
+    let hash = state
+        .get_block(height)
+        .map(|block| hex::encode(block.hash()))
+        .unwrap_or_default();
+    let dump = StateDump {
+        height,
+        hash,
+        firm_height: state.firm_height,
+        state_root: hex::encode(game_manager.state_root()),
+        state: game_manager,
+    };
+    std::fs::write(path, serde_json::to_vec_pretty(&dump)?)?;
+    println!("wrote state at block {} to {}", height, path.display());
+    Ok(())
+}
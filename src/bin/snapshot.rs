@@ -0,0 +1,39 @@
+use chess_rollup::config::Config;
+use chess_rollup::genesis::Genesis;
+use chess_rollup::snapshot::Snapshot;
+use chess_rollup::storage::Storage;
+use color_eyre::eyre::{self, eyre};
+use std::path::PathBuf;
+
+// Writes a snapshot of the firm state in the database named by the environment, for nodes that
+// are stopped. A running node serves the same snapshot at `GET /snapshot`, e.g.
+// `curl -o snapshot.json http://localhost:3030/snapshot`.
+const USAGE: &str = "usage: snapshot <file>
+
+  <file>  where to write the snapshot, as JSON";
+
+fn main() -> eyre::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = match (args.next(), args.next()) {
+        (Some(arg), None) if arg == "-h" || arg == "--help" => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        (Some(path), None) if !path.starts_with("--") => PathBuf::from(path),
+        _ => return Err(eyre!(USAGE)),
+    };
+    let config = Config::from_env()?;
+    let genesis = Genesis::load(&config.genesis_filepath)?;
+    let storage = Storage::open(&config.db_filepath)?;
+    let (app, _) = storage
+        .load(&genesis)?
+        .ok_or_else(|| eyre!("nothing committed to {}", config.db_filepath.display()))?;
+    let snapshot = Snapshot::export(&app).map_err(|e| eyre!(e))?;
+    snapshot.write(&path)?;
+    println!(
+        "wrote snapshot at block {} to {}",
+        snapshot.height,
+        path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use astria_core::generated::execution::v1 as execution;
+use bytes::{Buf, Bytes};
+use color_eyre::eyre::{self, WrapErr as _};
+use prost::Message;
+use std::fs::{File, OpenOptions};
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Execution API traffic kept on disk: a file of length-delimited `LogEntry` messages, one per
+// call, in the order the calls were served.
+
+#[derive(Clone, PartialEq, Message)]
+pub struct LogEntry {
+    #[prost(oneof = "Call", tags = "1, 2, 3")]
+    pub call: Option<Call>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum Call {
+    #[prost(message, tag = "1")]
+    ExecuteBlock(ExecuteBlockCall),
+    #[prost(message, tag = "2")]
+    UpdateCommitmentState(UpdateCommitmentStateCall),
+    #[prost(message, tag = "3")]
+    GetGenesisInfo(GetGenesisInfoCall),
+}
+
+// A response is absent when the call failed, in which case `error` holds the status message
+#[derive(Clone, PartialEq, Message)]
+pub struct ExecuteBlockCall {
+    #[prost(message, optional, tag = "1")]
+    pub request: Option<execution::ExecuteBlockRequest>,
+    #[prost(message, optional, tag = "2")]
+    pub response: Option<execution::Block>,
+    #[prost(string, tag = "3")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct UpdateCommitmentStateCall {
+    #[prost(message, optional, tag = "1")]
+    pub request: Option<execution::UpdateCommitmentStateRequest>,
+    #[prost(message, optional, tag = "2")]
+    pub response: Option<execution::CommitmentState>,
+    #[prost(string, tag = "3")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GetGenesisInfoCall {
+    #[prost(message, optional, tag = "1")]
+    pub request: Option<execution::GetGenesisInfoRequest>,
+    #[prost(message, optional, tag = "2")]
+    pub response: Option<execution::GenesisInfo>,
+    #[prost(string, tag = "3")]
+    pub error: String,
+}
+
+// Appends entries to a log file, keeping what an earlier run recorded
+pub struct LogWriter {
+    file: Mutex<File>,
+}
+
+impl LogWriter {
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err(format!("failed opening {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn append(&self, call: Call) -> eyre::Result<()> {
+        let entry = LogEntry { call: Some(call) };
+        let mut file = self.file.lock().expect("log writer lock poisoned");
+        file.write_all(&entry.encode_length_delimited_to_vec())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+// Read every length-delimited message of a file: a recorded log of `LogEntry`s, or an export
+// of bare requests
+pub fn read_messages<M: Message + Default>(path: &Path) -> eyre::Result<Vec<M>> {
+    let mut data =
+        Bytes::from(std::fs::read(path).wrap_err(format!("failed reading {}", path.display()))?);
+    let mut messages = Vec::new();
+    while data.has_remaining() {
+        let message = M::decode_length_delimited(&mut data)
+            .wrap_err(format!("failed decoding message {}", messages.len()))?;
+        messages.push(message);
+    }
+    Ok(messages)
+}
@@ -1,45 +1,82 @@
+use crate::block_log::LogWriter;
 use crate::config::Config;
+use crate::dev::{self, MockComposer, TransactionQueue};
 use crate::execution_service::{self, RollupExecutionService};
-use crate::game::{self, GameManager};
+use crate::game::GameManager;
+use crate::genesis::Genesis;
+use crate::rest;
 use crate::rollup_app::AppState;
+use crate::snapshot::Snapshot;
+use crate::storage::Storage;
 use astria_core::generated::composer::v1::grpc_collector_service_client::GrpcCollectorServiceClient;
-use astria_core::generated::composer::v1::{
-    SubmitRollupTransactionRequest, SubmitRollupTransactionResponse,
-};
+use astria_core::generated::composer::v1::grpc_collector_service_server::GrpcCollectorServiceServer;
 use astria_core::generated::execution::v1::execution_service_server::{
     ExecutionService, ExecutionServiceServer,
 };
-use astria_core::generated::primitive::v1::RollupId;
-use bytes::Bytes;
-use color_eyre::eyre;
+use astria_core::Protobuf;
+use color_eyre::eyre::{self, eyre};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use warp::Filter;
 pub struct Chess;
-use crate::game::Transaction;
 use std::net::SocketAddr;
-use tonic::transport::Server;
+use std::time::Duration;
+use tonic::transport::{Endpoint, Server, Uri};
 impl Chess {
     pub async fn run_until_stopped(mut config: Config) -> eyre::Result<()> {
         let addr: SocketAddr = config.grpc_addr.parse()?;
+        let genesis = Genesis::load(&config.genesis_filepath)?;
+        println!("rollup id: {}", hex::encode(genesis.rollup_id().get()));
         let composer_addr = config.composer_addr;
         println!("composer address: {}", composer_addr);
-        let mut composer_client = GrpcCollectorServiceClient::connect(composer_addr.clone())
-            .await
-            .unwrap();
-        let game_manager = GameManager::new(Bytes::from_static(&[69_u8; 32]));
-        // `POST /create_game` endpoint to create a new game
-        let create_game = warp::path!("create_game" / u32)
-            .and(warp::post())
-            .and(with_composer(composer_client.clone()))
-            .and_then(handle_create_game);
-        // `GET /game_status/{game_id}` endpoint to get game status
-        let game_status = warp::path!("game_status" / u32)
-            .and(warp::get())
-            .and(with_game_manager(game_manager.clone()))
-            .and_then(handle_get_game_status);
-
-        let routes = create_game;
+        let queue = TransactionQueue::default();
+        // Connect lazily, so the node starts even if the composer is not up yet
+        let composer_endpoint = Endpoint::from_shared(composer_addr)?;
+        if config.dev_mode {
+            // Serve the mock composer where the REST API expects the real one
+            let mock_addr = resolve(composer_endpoint.uri()).await?;
+            let mock_composer = MockComposer {
+                queue: queue.clone(),
+            };
+            println!("dev mode: mock composer listening on {}", mock_addr);
+            tokio::spawn(async move {
+                if let Err(e) = Server::builder()
+                    .add_service(GrpcCollectorServiceServer::new(mock_composer))
+                    .serve(mock_addr)
+                    .await
+                {
+                    eprintln!("dev mode: mock composer stopped: {}", e);
+                }
+            });
+        }
+        let composer_client = GrpcCollectorServiceClient::new(composer_endpoint.connect_lazy());
+        // Resume from the database if a previous run committed any blocks
+        let storage = Storage::open(&config.db_filepath)?;
+        let (app, game_manager) = match storage.load(&genesis)? {
+            Some(restored) => restored,
+            None => match &config.snapshot_filepath {
+                Some(path) => {
+                    let snapshot = Snapshot::load(path)?;
+                    snapshot.verify(&genesis)?;
+                    println!("starting from snapshot at block {}", snapshot.height);
+                    let (app, game_manager) = snapshot.into_state(&genesis)?;
+                    storage.commit_snapshot(&app)?;
+                    (app, game_manager)
+                }
+                None => {
+                    let (app, game_manager) = genesis_state(&genesis)?;
+                    storage.commit_game_state(0, &game_manager)?;
+                    (app, game_manager)
+                }
+            },
+        };
+        // Single authoritative game state: written by the execution service, read by the REST API
+        let game_manager = Arc::new(RwLock::new(game_manager));
+        let mutex_app = Arc::new(RwLock::new(app));
+        let composer = rest::Composer {
+            client: composer_client,
+            rollup_id: genesis.rollup_id().into_raw(),
+        };
+        let routes = rest::routes(mutex_app.clone(), game_manager.clone(), composer);
 
         println!("Rest server listening on {}", 3030);
         // Spawn the server in a separate async task so it doesn't block the main program
@@ -47,18 +84,33 @@ impl Chess {
             warp::serve(routes).run(([0, 0, 0, 0], 3030)).await;
         });
 
-        let app = AppState::new();
-        let mut game_manager = game::GameManager::new(Bytes::from_static(&[69_u8; 32]));
-        let mutex_game_manager = Arc::new(RwLock::new(game_manager));
-        let mutex_app = Arc::new(RwLock::new(app));
-        let execution_service = RollupExecutionService {
-            app: mutex_app,
-            game_manager: mutex_game_manager,
+        let recorder = match &config.record_log_filepath {
+            Some(path) => {
+                println!("recording execution API calls to {}", path.display());
+                Some(LogWriter::open(path)?)
+            }
+            None => None,
         };
+        let execution_service = Arc::new(RollupExecutionService {
+            app: mutex_app,
+            game_manager,
+            storage,
+            genesis_info: genesis.genesis_info(),
+            recorder,
+        });
+        if config.dev_mode {
+            let block_time = Duration::from_millis(config.dev_block_time_ms);
+            println!("dev mode: producing a block every {:?}", block_time);
+            tokio::spawn(dev::produce_blocks(
+                execution_service.clone(),
+                queue,
+                block_time,
+            ));
+        }
 
         println!("ExecutionServiceServer listening on {}", addr);
         Server::builder()
-            .add_service(ExecutionServiceServer::new(execution_service))
+            .add_service(ExecutionServiceServer::from_arc(execution_service))
             .serve(addr)
             .await?;
 
@@ -66,60 +118,27 @@ impl Chess {
     }
 }
 
-// Helper function to pass `GameManager` as a filter to endpoints
-fn with_composer(
-    composer_client: GrpcCollectorServiceClient<tonic::transport::channel::Channel>,
-) -> impl Filter<
-    Extract = (GrpcCollectorServiceClient<tonic::transport::channel::Channel>,),
-    Error = std::convert::Infallible,
-> + Clone {
-    warp::any().map(move || composer_client.clone())
-}
-
-// Helper function to pass `GameManager` as a filter to endpoints
-fn with_game_manager(
-    game_manager: game::GameManager,
-) -> impl Filter<Extract = (game::GameManager,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || game_manager.clone())
+// App and game state before the first block
+pub fn genesis_state(genesis: &Genesis) -> eyre::Result<(AppState, GameManager)> {
+    let mut app = genesis.app_state()?;
+    let game_manager = genesis.game_manager()?;
+    // Keep the genesis state so the first block can be re-executed
+    app.add_game_state(0, game_manager.clone());
+    Ok((app, game_manager))
 }
 
-// Handler for `POST /create_game/{game_id}`
-async fn handle_create_game(
-    game_id: u32,
-    mut composer_client: GrpcCollectorServiceClient<tonic::transport::channel::Channel>,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let transaction = Transaction::StartGame { game_id: game_id };
-    println!("encoding transaction: {:?}", transaction);
-    let encoded_transaction = transaction.encode();
-    println!(
-        "submitting transaction to sequencer... encoded transaction {:?}",
-        encoded_transaction
-    );
-    let composer_response = composer_client
-        .submit_rollup_transaction(SubmitRollupTransactionRequest {
-            rollup_id: Some(RollupId {
-                inner: Bytes::from_static(&[69_u8; 32]),
-            }),
-            data: encoded_transaction,
-        })
-        .await
-        .unwrap();
-    Ok(warp::reply::json(&format!(
-        "Game {} transaction submitted to sequencer",
-        game_id
-    )))
-}
-
-// Handler for `GET /game_status/{game_id}`
-async fn handle_get_game_status(
-    game_id: u32,
-    game_manager: game::GameManager,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    match game_manager.game_status(game_id) {
-        Ok(status) => {
-            let response = format!("{:?}", status);
-            Ok(warp::reply::json(&response))
-        }
-        Err(_) => Err(warp::reject::not_found()),
-    }
+// Socket address named by a URI, resolving host names such as `localhost`
+async fn resolve(uri: &Uri) -> eyre::Result<SocketAddr> {
+    let host = uri
+        .host()
+        .ok_or_else(|| eyre!("address {} has no host", uri))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = uri
+        .port_u16()
+        .ok_or_else(|| eyre!("address {} has no port", uri))?;
+    tokio::net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| eyre!("address {} does not resolve", uri))
 }
@@ -5,10 +5,17 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Config {
-    /// The path to penumbra storage db.
+    /// The path to the rollup state database.
     pub db_filepath: PathBuf,
     /// Log level: debug, info, warn, or error
     pub log: String,
+    /// The path to the genesis file, JSON or TOML. The container image ships one at
+    /// `/etc/chess/genesis.json`.
+    pub genesis_filepath: PathBuf,
+    /// Snapshot to start from when the database is empty, instead of genesis. Written by
+    /// `GET /snapshot` or the `snapshot` binary.
+    #[serde(default)]
+    pub snapshot_filepath: Option<PathBuf>,
     /// The gRPC endpoint
     pub grpc_addr: String,
     /// Forces writing trace data to stdout no matter if connected to a tty or not.
@@ -17,6 +24,20 @@ pub struct Config {
     pub pretty_print: bool,
     /// The address of the Composer service.
     pub composer_addr: String,
+    /// Run without a sequencer: serve a mock composer at `composer_addr` and produce blocks
+    /// from the transactions it receives.
+    #[serde(default)]
+    pub dev_mode: bool,
+    /// Interval between blocks in dev mode, in milliseconds.
+    #[serde(default = "default_dev_block_time_ms")]
+    pub dev_block_time_ms: u64,
+    /// File every execution API call is appended to; nothing is recorded when unset.
+    #[serde(default)]
+    pub record_log_filepath: Option<PathBuf>,
+}
+
+fn default_dev_block_time_ms() -> u64 {
+    1000
 }
 
 impl Config {
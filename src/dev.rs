@@ -0,0 +1,102 @@
+use crate::execution_service::RollupExecutionService;
+use astria_core::generated::composer::v1::grpc_collector_service_server::GrpcCollectorService;
+use astria_core::generated::composer::v1::{
+    SubmitRollupTransactionRequest, SubmitRollupTransactionResponse,
+};
+use astria_core::generated::execution::v1 as execution;
+use astria_core::generated::execution::v1::execution_service_server::ExecutionService;
+use astria_core::generated::sequencerblock::v1::rollup_data::Value::SequencedData;
+use astria_core::generated::sequencerblock::v1::RollupData;
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+
+// Stand-in for the composer, sequencer and conductor when running on a single machine:
+// submitted transactions are queued and sequenced into the next block on a timer.
+
+// Transactions submitted since the last block, in arrival order
+pub type TransactionQueue = Arc<Mutex<Vec<Bytes>>>;
+
+pub struct MockComposer {
+    pub queue: TransactionQueue,
+}
+
+#[async_trait::async_trait]
+impl GrpcCollectorService for MockComposer {
+    async fn submit_rollup_transaction(
+        self: Arc<Self>,
+        request: Request<SubmitRollupTransactionRequest>,
+    ) -> Result<Response<SubmitRollupTransactionResponse>, Status> {
+        let request = request.into_inner();
+        self.queue.lock().await.push(request.data);
+        Ok(Response::new(SubmitRollupTransactionResponse {}))
+    }
+}
+
+// Execute a block of the queued transactions every `block_time`, making each one soft and
+// firm right away
+pub async fn produce_blocks(
+    service: Arc<RollupExecutionService>,
+    queue: TransactionQueue,
+    block_time: Duration,
+) {
+    let mut interval = tokio::time::interval(block_time);
+    loop {
+        interval.tick().await;
+        let transactions: Vec<RollupData> = std::mem::take(&mut *queue.lock().await)
+            .into_iter()
+            .map(|data| RollupData {
+                value: Some(SequencedData(data)),
+            })
+            .collect();
+        let (prev_block_hash, base_celestia_height) = {
+            let app = service.app.read().await;
+            let Some(soft) = app.get_block(app.soft_height) else {
+                eprintln!("dev mode: soft block {} not found", app.soft_height);
+                continue;
+            };
+            (soft.hash().clone(), app.celestia_height)
+        };
+        let request = execution::ExecuteBlockRequest {
+            prev_block_hash,
+            transactions,
+            timestamp: Some(now()),
+        };
+        let block = match service.clone().execute_block(Request::new(request)).await {
+            Ok(response) => response.into_inner(),
+            Err(status) => {
+                eprintln!("dev mode: failed executing block: {}", status.message());
+                continue;
+            }
+        };
+        let request = execution::UpdateCommitmentStateRequest {
+            commitment_state: Some(execution::CommitmentState {
+                soft: Some(block.clone()),
+                firm: Some(block),
+                base_celestia_height,
+            }),
+        };
+        if let Err(status) = service
+            .clone()
+            .update_commitment_state(Request::new(request))
+            .await
+        {
+            eprintln!(
+                "dev mode: failed updating commitment state: {}",
+                status.message()
+            );
+        }
+    }
+}
+
+fn now() -> pbjson_types::Timestamp {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    pbjson_types::Timestamp {
+        seconds: elapsed.as_secs() as i64,
+        nanos: elapsed.subsec_nanos() as i32,
+    }
+}
@@ -1,20 +1,27 @@
-use crate::game;
+use crate::accounts;
+use crate::block_log::{
+    Call, ExecuteBlockCall, GetGenesisInfoCall, LogWriter, UpdateCommitmentStateCall,
+};
+use crate::game::{self, ExecutedBlock, GameManager, RollupInput};
 use crate::rollup_app::AppState;
+use crate::storage::Storage;
 use astria_core::execution::v1::Block;
 use astria_core::generated::execution::v1 as execution;
 use astria_core::generated::execution::v1::execution_service_server::ExecutionService;
 use astria_core::generated::sequencerblock::v1::rollup_data::Value::{Deposit, SequencedData};
-use astria_core::primitive::v1::RollupId;
+use astria_core::generated::sequencerblock::v1::RollupData;
 use astria_core::Protobuf;
-use bytes::Bytes;
 use chess::GameResult;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tonic::{Request, Response, Status};
 
-pub(crate) struct RollupExecutionService {
+pub struct RollupExecutionService {
     pub app: Arc<RwLock<AppState>>,
     pub game_manager: Arc<RwLock<game::GameManager>>,
+    pub storage: Storage,
+    pub genesis_info: execution::GenesisInfo,
+    pub recorder: Option<LogWriter>, // Set when execution API calls are recorded
 }
 
 #[async_trait::async_trait]
@@ -24,13 +31,16 @@ impl ExecutionService for RollupExecutionService {
         request: Request<execution::GetGenesisInfoRequest>,
     ) -> Result<Response<execution::GenesisInfo>, Status> {
         println!("getting genesis info:");
-        let _request = request.into_inner();
-        let genesis_info = execution::GenesisInfo {
-            rollup_id: Some(RollupId::new([69u8; 32]).into_raw()),
-            sequencer_genesis_block_height: 2,
-            celestia_block_variance: 100,
-        };
+        let request = request.into_inner();
+        let genesis_info = self.genesis_info.clone();
         println!("genesis_info: {:?}", genesis_info);
+        self.log_call(|| {
+            Call::GetGenesisInfo(GetGenesisInfoCall {
+                request: Some(request),
+                response: Some(genesis_info.clone()),
+                error: String::new(),
+            })
+        });
         Ok(Response::new(genesis_info))
     }
 
@@ -41,21 +51,11 @@ impl ExecutionService for RollupExecutionService {
         println!("getting block:");
         let state = self.app.read().await;
         let request = request.into_inner();
-        match request.identifier {
-            Some(identidfier) => match identidfier.identifier {
-                Some(id) => match id {
-                    execution::block_identifier::Identifier::BlockNumber(height) => {
-                        let block: Block = state.get_block(height).unwrap().to_owned();
-                        Ok(Response::new(block.into_raw()))
-                    }
-                    execution::block_identifier::Identifier::BlockHash(_) => {
-                        Err(Status::unimplemented("Get Block by hash not implemented"))
-                    }
-                },
-                None => Err(Status::invalid_argument("missing identifier")),
-            },
-            None => Err(Status::invalid_argument("missing block identifier")),
-        }
+        let identifier = request
+            .identifier
+            .ok_or_else(|| Status::invalid_argument("missing block identifier"))?;
+        let block = find_block(&state, identifier)?;
+        Ok(Response::new(block.to_owned().into_raw()))
     }
 
     async fn batch_get_blocks(
@@ -66,17 +66,7 @@ impl ExecutionService for RollupExecutionService {
         let state = self.app.read().await;
         let mut blocks = Vec::new();
         for identifier in request.identifiers {
-            match identifier.identifier {
-                Some(id) => match id {
-                    execution::block_identifier::Identifier::BlockNumber(block_number) => {
-                        blocks.push(state.get_block(block_number).unwrap().to_owned().into_raw());
-                    }
-                    execution::block_identifier::Identifier::BlockHash(_) => {
-                        return Err(Status::unimplemented("Get Block by hash not implemented"))
-                    }
-                },
-                None => return Err(Status::invalid_argument("missing block identifier")),
-            }
+            blocks.push(find_block(&state, identifier)?.to_owned().into_raw());
         }
         Ok(Response::new(execution::BatchGetBlocksResponse { blocks }))
     }
@@ -86,24 +76,17 @@ impl ExecutionService for RollupExecutionService {
         request: Request<execution::ExecuteBlockRequest>,
     ) -> Result<Response<execution::Block>, Status> {
         let request = request.into_inner();
-        let timestamp = request.timestamp.unwrap();
-        let mut transactions: Vec<Bytes> = Vec::new();
-        for rollup_data in request.transactions {
-            match rollup_data.value {
-                Some(value) => match value {
-                    SequencedData(data) => transactions.push(data),
-                    Deposit(_) => {}
-                },
-                None => {}
-            };
-        }
         let mut state = self.app.write().await;
-        let mut game_manager = self.game_manager.write().await;
-        let hash =
-            game_manager.process_transactions(&transactions, request.prev_block_hash.clone());
-        let soft_height = state.soft_height;
-        let block = state.new_block(request.prev_block_hash, hash, soft_height + 1, timestamp);
-        Ok(Response::new(block))
+        // Recorded while the state is still locked, so the log follows execution order
+        let result = self.execute_and_commit(&mut state, request.clone()).await;
+        self.log_call(|| {
+            Call::ExecuteBlock(ExecuteBlockCall {
+                request: Some(request),
+                response: result.as_ref().ok().cloned(),
+                error: error_message(&result),
+            })
+        });
+        result.map(Response::new)
     }
 
     async fn get_commitment_state(
@@ -129,36 +112,402 @@ impl ExecutionService for RollupExecutionService {
         self: Arc<Self>,
         request: Request<execution::UpdateCommitmentStateRequest>,
     ) -> Result<Response<execution::CommitmentState>, Status> {
+        let request = request.into_inner();
         let mut state = self.app.write().await;
-        let commitment_state_request = request.into_inner().commitment_state.unwrap();
-        let soft_block_request = commitment_state_request.soft.as_ref().unwrap();
-        let firm_block_request = commitment_state_request.firm.as_ref().unwrap();
-        let soft_request = soft_block_request.number;
-        let firm_request = firm_block_request.number;
-        let soft_block = state.get_block(soft_request).unwrap().to_owned();
-        let firm_block = state.get_block(firm_request).unwrap().to_owned();
-        if soft_block.hash().to_owned() != soft_block_request.hash {
-            println!(
-                "soft block hash does not match: current: {:?},  request: {:?}",
-                soft_block.hash().to_owned(),
-                soft_block_request.hash
-            );
-            return Err(Status::invalid_argument("Soft block hash does not match"));
-        }
-        if firm_block.hash().to_owned() != firm_block_request.hash {
-            return Err(Status::invalid_argument("Firm block hash does not match"));
-        }
-        state.soft_height = soft_request;
-        state.firm_height = firm_request;
-        let new_commitment_state = execution::CommitmentState {
-            soft: Some(soft_block_request.to_owned()),
-            firm: Some(firm_block_request.to_owned()),
-            base_celestia_height: commitment_state_request.base_celestia_height,
-        };
+        let result = self.commit_commitment(&mut state, request.clone()).await;
+        self.log_call(|| {
+            Call::UpdateCommitmentState(UpdateCommitmentStateCall {
+                request: Some(request),
+                response: result.as_ref().ok().cloned(),
+                error: error_message(&result),
+            })
+        });
+        result.map(Response::new)
+    }
+}
+
+impl RollupExecutionService {
+    // Execute a block, persist it and make it the latest state. The app state is left
+    // untouched unless the block was persisted.
+    async fn execute_and_commit(
+        &self,
+        state: &mut AppState,
+        request: execution::ExecuteBlockRequest,
+    ) -> Result<execution::Block, Status> {
+        let execution = execute_request(state, request)?;
+        self.storage
+            .commit_block(
+                state,
+                &execution.block,
+                &execution.executed.roots,
+                &execution.executed.body,
+                &execution.game_manager,
+                &execution.reverted,
+            )
+            .map_err(|e| Status::internal(format!("failed persisting block: {:?}", e)))?;
+        *self.game_manager.write().await = execution.game_manager.clone();
+        let block = execution.block.clone();
+        execution.record(state);
+        Ok(block)
+    }
+
+    // Move the commitment heights and persist them
+    async fn commit_commitment(
+        &self,
+        state: &mut AppState,
+        request: execution::UpdateCommitmentStateRequest,
+    ) -> Result<execution::CommitmentState, Status> {
+        let commitment_state_request = request
+            .commitment_state
+            .ok_or_else(|| Status::invalid_argument("missing commitment state"))?;
+        // Persist before touching `state`, so a failed write leaves memory as it was
+        let commitment = check_commitment(state, &commitment_state_request)?;
+        self.storage
+            .commit_heights(
+                commitment.soft_height,
+                commitment.firm_height,
+                commitment.celestia_height,
+            )
+            .map_err(|e| Status::internal(format!("failed persisting commitment: {:?}", e)))?;
+        commitment.record(state);
 
         let games = self.game_manager.read().await;
         let game_state = games.game_status(0);
         println!("game 0 state: {:?}", game_state);
-        Ok(Response::new(new_commitment_state))
+        Ok(commitment_state_request)
+    }
+
+    // Append a call to the record log, if recording is enabled. A failed write is reported
+    // but does not fail the call.
+    fn log_call(&self, call: impl FnOnce() -> Call) {
+        if let Some(recorder) = &self.recorder {
+            if let Err(e) = recorder.append(call()) {
+                eprintln!("failed recording execution API call: {:?}", e);
+            }
+        }
+    }
+}
+
+// Status message of a failed call, empty on success
+fn error_message<T>(result: &Result<T, Status>) -> String {
+    result
+        .as_ref()
+        .err()
+        .map(|status| status.message().to_string())
+        .unwrap_or_default()
+}
+
+// A block executed on top of its parent, added to the app state once it is persisted
+#[derive(Clone)]
+pub struct BlockExecution {
+    pub block: execution::Block,
+    pub executed: ExecutedBlock,
+    pub game_manager: GameManager, // Game state after the block
+    pub reverted: Vec<u32>,        // Heights of the blocks it replaces
+}
+
+impl BlockExecution {
+    pub fn height(&self) -> u32 {
+        self.block.number
+    }
+
+    // Roll back the blocks it replaces, then add the block with its game state, roots and body
+    pub fn record(self, state: &mut AppState) {
+        let height = self.height();
+        if !self.reverted.is_empty() {
+            state.truncate(height - 1);
+        }
+        state.new_block(
+            self.block.parent_block_hash,
+            self.block.hash,
+            height,
+            self.block.timestamp.unwrap_or_default(),
+        );
+        state.add_game_state(height, self.game_manager);
+        state.block_roots.insert(height, self.executed.roots);
+        state.add_body(height, self.executed.body);
+    }
+}
+
+// Execute a block on top of the parent named by the conductor, which is the soft head unless
+// it is re-executing a block that is not yet firm, in which case later blocks are to be rolled
+// back. Nothing is changed in `state`, see `BlockExecution::record`.
+pub fn execute_request(
+    state: &AppState,
+    request: execution::ExecuteBlockRequest,
+) -> Result<BlockExecution, Status> {
+    let timestamp = request
+        .timestamp
+        .ok_or_else(|| Status::invalid_argument("missing timestamp"))?;
+    let transactions = rollup_inputs(request.transactions);
+    let parent_height = state
+        .get_block_by_hash(&request.prev_block_hash)
+        .map(|parent| parent.number())
+        .ok_or_else(|| {
+            Status::failed_precondition(format!(
+                "unknown parent block {}",
+                hex::encode(&request.prev_block_hash)
+            ))
+        })?;
+    if parent_height < state.firm_height {
+        return Err(Status::failed_precondition(format!(
+            "parent block {} is below firm height {}",
+            parent_height, state.firm_height
+        )));
+    }
+    // Execute against a copy of the parent's game state so readers are only blocked for the swap
+    let mut game_manager = state
+        .get_game_state(parent_height)
+        .cloned()
+        .ok_or_else(|| {
+            Status::failed_precondition(format!("no game state kept for block {}", parent_height))
+        })?;
+    let reverted: Vec<u32> = (parent_height + 1..=state.soft_height).collect();
+    if !reverted.is_empty() {
+        println!(
+            "re-executing block {}, rolling back from soft height {}",
+            parent_height + 1,
+            state.soft_height
+        );
+    }
+    let height = parent_height + 1;
+    let timestamp_ms = game::timestamp_ms(&timestamp);
+    let executed = game_manager.process_transactions(
+        &transactions,
+        request.prev_block_hash.clone(),
+        height,
+        timestamp_ms,
+    );
+    let block = execution::Block {
+        number: height,
+        hash: executed.hash.clone(),
+        parent_block_hash: request.prev_block_hash,
+        timestamp: Some(timestamp),
+    };
+    Ok(BlockExecution {
+        block,
+        executed,
+        game_manager,
+        reverted,
+    })
+}
+
+// Sequenced data and deposits of a block, in sequencer order. Missing deposit fields are left
+// empty, which `Accounts::apply_deposit` rejects as an invalid deposit.
+fn rollup_inputs(transactions: Vec<RollupData>) -> Vec<RollupInput> {
+    let mut inputs = Vec::new();
+    for rollup_data in transactions {
+        match rollup_data.value {
+            Some(SequencedData(data)) => inputs.push(RollupInput::Transaction(data)),
+            Some(Deposit(deposit)) => inputs.push(RollupInput::Deposit(accounts::Deposit {
+                destination: deposit.destination_chain_address,
+                asset: deposit.asset,
+                amount: deposit
+                    .amount
+                    .map(|amount| (u128::from(amount.hi) << 64) | u128::from(amount.lo))
+                    .unwrap_or(0),
+                source_transaction_id: deposit
+                    .source_transaction_id
+                    .map(|id| id.inner)
+                    .unwrap_or_default(),
+                source_action_index: deposit.source_action_index,
+            })),
+            None => {}
+        }
+    }
+    inputs
+}
+
+// Heights named by the conductor, checked against the blocks in the app state
+#[derive(Debug, Clone, Copy)]
+pub struct Commitment {
+    pub soft_height: u32,
+    pub firm_height: u32,
+    pub celestia_height: u64,
+}
+
+impl Commitment {
+    // Move the heights and forget game states below the new firm height
+    pub fn record(self, state: &mut AppState) {
+        state.soft_height = self.soft_height;
+        state.firm_height = self.firm_height;
+        state.celestia_height = self.celestia_height;
+        state.prune_game_states();
+    }
+}
+
+// Move the soft and firm heights to the blocks named by the conductor, checking their hashes
+pub fn apply_commitment(
+    state: &mut AppState,
+    commitment_state_request: &execution::CommitmentState,
+) -> Result<(), Status> {
+    check_commitment(state, commitment_state_request)?.record(state);
+    Ok(())
+}
+
+// Check the blocks named by the conductor without changing `state`
+pub fn check_commitment(
+    state: &AppState,
+    commitment_state_request: &execution::CommitmentState,
+) -> Result<Commitment, Status> {
+    let soft_block_request = commitment_state_request
+        .soft
+        .as_ref()
+        .ok_or_else(|| Status::invalid_argument("missing soft block"))?;
+    let firm_block_request = commitment_state_request
+        .firm
+        .as_ref()
+        .ok_or_else(|| Status::invalid_argument("missing firm block"))?;
+    let soft_request = soft_block_request.number;
+    let firm_request = firm_block_request.number;
+    let soft_block = state
+        .get_block(soft_request)
+        .ok_or_else(|| Status::not_found(format!("soft block {} not found", soft_request)))?
+        .to_owned();
+    let firm_block = state
+        .get_block(firm_request)
+        .ok_or_else(|| Status::not_found(format!("firm block {} not found", firm_request)))?
+        .to_owned();
+    if soft_block.hash().to_owned() != soft_block_request.hash {
+        println!(
+            "soft block hash does not match: current: {:?},  request: {:?}",
+            soft_block.hash().to_owned(),
+            soft_block_request.hash
+        );
+        return Err(Status::invalid_argument("Soft block hash does not match"));
+    }
+    if firm_block.hash().to_owned() != firm_block_request.hash {
+        return Err(Status::invalid_argument("Firm block hash does not match"));
+    }
+    Ok(Commitment {
+        soft_height: soft_request,
+        firm_height: firm_request,
+        celestia_height: commitment_state_request.base_celestia_height,
+    })
+}
+
+// Resolve a block by number or hash
+fn find_block(state: &AppState, identifier: execution::BlockIdentifier) -> Result<&Block, Status> {
+    match identifier.identifier {
+        Some(execution::block_identifier::Identifier::BlockNumber(height)) => state
+            .get_block(height)
+            .ok_or_else(|| Status::not_found(format!("block {} not found", height))),
+        Some(execution::block_identifier::Identifier::BlockHash(hash)) => state
+            .get_block_by_hash(&hash)
+            .ok_or_else(|| Status::not_found(format!("block {} not found", hex::encode(&hash)))),
+        None => Err(Status::invalid_argument("missing identifier")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{SignedTransaction, Transaction};
+    use astria_core::crypto::SigningKey;
+    use bytes::Bytes;
+
+    fn genesis() -> AppState {
+        let mut app = AppState::new(
+            Bytes::from(vec![0u8; 32]),
+            pbjson_types::Timestamp::default(),
+            0,
+        );
+        app.add_game_state(0, GameManager::default());
+        app
+    }
+
+    fn request(
+        prev_block_hash: Bytes,
+        transactions: Vec<RollupData>,
+    ) -> execution::ExecuteBlockRequest {
+        execution::ExecuteBlockRequest {
+            prev_block_hash,
+            transactions,
+            timestamp: Some(pbjson_types::Timestamp::default()),
+        }
+    }
+
+    fn start_game(game_id: u32) -> RollupData {
+        let signing_key = SigningKey::from([1u8; 32]);
+        let transaction = Transaction::StartGame {
+            game_id,
+            time_control: None,
+            stake: None,
+        };
+        let rollup_id = GameManager::default().params.rollup_id;
+        let signature = signing_key.sign(&SignedTransaction::signing_bytes(
+            &rollup_id,
+            0,
+            &transaction,
+        ));
+        let signed_tx = SignedTransaction {
+            public_key: signing_key.verification_key().to_bytes(),
+            signature: signature.to_bytes(),
+            nonce: 0,
+            transaction,
+        };
+        RollupData {
+            value: Some(SequencedData(signed_tx.encode())),
+        }
+    }
+
+    // Execute empty blocks 1 to `height` on top of genesis, returning their hashes
+    fn chain(app: &mut AppState, height: u32) -> Vec<Bytes> {
+        let mut hashes = vec![app.get_block(0).unwrap().hash().clone()];
+        for _ in 0..height {
+            let parent = hashes.last().unwrap().clone();
+            let execution = execute_request(app, request(parent, Vec::new())).unwrap();
+            hashes.push(execution.block.hash.clone());
+            execution.record(app);
+        }
+        hashes
+    }
+
+    #[test]
+    fn re_executing_a_block_replaces_it_and_later_blocks() {
+        let mut app = genesis();
+        let hashes = chain(&mut app, 3);
+        let execution =
+            execute_request(&app, request(hashes[1].clone(), vec![start_game(1)])).unwrap();
+        assert_eq!(execution.reverted, vec![2, 3]);
+        assert_ne!(execution.block.hash, hashes[2]);
+        // Nothing changes until the execution is recorded
+        assert_eq!(app.soft_height, 3);
+        assert!(app.get_block(3).is_some());
+
+        let replacement = execution.block.hash.clone();
+        execution.record(&mut app);
+        assert_eq!(app.soft_height, 2);
+        assert!(app.get_block(3).is_none());
+        assert!(app.get_block_by_hash(&hashes[2]).is_none());
+        assert!(app.get_block_by_hash(&hashes[3]).is_none());
+        assert_eq!(app.get_block(2).unwrap().hash(), &replacement);
+        assert_eq!(app.get_block_body(2).unwrap().receipts.len(), 1);
+        assert!(app.get_game_state(2).unwrap().game_status(1).is_ok());
+        assert!(app.get_game_state(3).is_none());
+    }
+
+    #[test]
+    fn unknown_parents_are_rejected() {
+        let mut app = genesis();
+        chain(&mut app, 2);
+        let error = execute_request(&app, request(Bytes::from(vec![9u8; 32]), Vec::new()))
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(app.soft_height, 2);
+    }
+
+    #[test]
+    fn parents_below_firm_are_rejected() {
+        let mut app = genesis();
+        let hashes = chain(&mut app, 3);
+        app.set_firm_height(2);
+        let error = execute_request(&app, request(hashes[1].clone(), Vec::new()))
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), tonic::Code::FailedPrecondition);
+        // The firm block itself may still be built on
+        assert!(execute_request(&app, request(hashes[2].clone(), Vec::new())).is_ok());
     }
 }
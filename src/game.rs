@@ -1,178 +1,1610 @@
-use chess::{Board, ChessMove, Color, Game, GameResult};
+use crate::accounts::{Accounts, Deposit};
+use crate::merkle;
+use crate::notation;
+use crate::receipts::{
+    BlockBody, BlockTransaction, DecodedInput, ErrorCode, Event, Receipt, TxError,
+};
+use crate::transaction::{transaction_hash, DrawClaim, SignedTransaction, Transaction};
+use bytes::Bytes;
+use chess::{Board, ChessMove, Color, Game, GameResult, Piece};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone)]
+// Players are identified by their ed25519 verification key
+pub type PublicKey = [u8; 32];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
+    #[serde(with = "notation::serde_fen")]
     pub game: Game,
-    pub winner: Option<Color>, // None if the game is ongoing, Some(Color) if won
+    pub white: PublicKey,         // Player who started the game
+    pub black: Option<PublicKey>, // Player who joined the game, None until someone joins
+    pub status: GameStatus,       // Where the game is in its lifecycle
+    #[serde(with = "notation::serde_opt_color")]
+    pub draw_offer: Option<Color>, // Side with a pending draw offer
+    pub time_control: Option<TimeControl>, // None for untimed games
+    pub clock: ClockState,
+    pub escrow: Option<Escrow>, // Stakes of a wagered game, None for friendly games
+    #[serde(with = "notation::serde_uci_moves")]
     pub moves: Vec<ChessMove>, // Keeps track of moves made in the game
+    pub last_updated: u32,      // Block height of the last change to this game
 }
 
-use bytes::{Buf, BufMut, Bytes, BytesMut};
-use std::convert::TryFrom;
+impl GameState {
+    // Winning color, None while the game is undecided or drawn
+    pub fn winner(&self) -> Option<Color> {
+        match self.status {
+            GameStatus::Finished {
+                result: Outcome::WhiteWins,
+                ..
+            } => Some(Color::White),
+            GameStatus::Finished {
+                result: Outcome::BlackWins,
+                ..
+            } => Some(Color::Black),
+            _ => None,
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub enum Transaction {
-    StartGame { game_id: u32 },
-    MakeMove { game_id: u32, move_san: String }, // SAN (Standard Algebraic Notation) for chess moves
+    pub fn is_over(&self) -> bool {
+        matches!(
+            self.status,
+            GameStatus::Finished { .. } | GameStatus::Aborted
+        )
+    }
+
+    // Player expected to move next, None while no opponent has joined
+    pub fn player_to_move(&self) -> Option<PublicKey> {
+        match self.game.side_to_move() {
+            Color::White => Some(self.white),
+            Color::Black => self.black,
+        }
+    }
+
+    // Color played by `player`, None if they are not part of this game
+    pub fn color_of(&self, player: PublicKey) -> Option<Color> {
+        if player == self.white {
+            Some(Color::White)
+        } else if Some(player) == self.black {
+            Some(Color::Black)
+        } else {
+            None
+        }
+    }
+
+    // How often the current position has occurred, including now
+    pub fn repetition_count(&self) -> usize {
+        let mut board = Board::default();
+        let mut hashes = vec![board.get_hash()];
+        for chess_move in &self.moves {
+            board = board.make_move_new(*chess_move);
+            hashes.push(board.get_hash());
+        }
+        let current = board.get_hash();
+        hashes.iter().filter(|hash| **hash == current).count()
+    }
+
+    // Half-moves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> usize {
+        let mut board = Board::default();
+        let mut clock = 0;
+        for chess_move in &self.moves {
+            let resets = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+                || board.piece_on(chess_move.get_dest()).is_some();
+            clock = if resets { 0 } else { clock + 1 };
+            board = board.make_move_new(*chess_move);
+        }
+        clock
+    }
+
+    // Remaining clock time for `color` at block time `now_ms`, None for games without a clock
+    pub fn remaining_ms(&self, color: Color, now_ms: u64) -> Option<u64> {
+        let Some(TimeControl::Clock { .. }) = self.time_control else {
+            return None;
+        };
+        let remaining = match color {
+            Color::White => self.clock.white_ms,
+            Color::Black => self.clock.black_ms,
+        };
+        if self.status == GameStatus::Active && self.game.side_to_move() == color {
+            let elapsed = now_ms.saturating_sub(self.clock.turn_started_ms);
+            Some(remaining.saturating_sub(elapsed))
+        } else {
+            Some(remaining)
+        }
+    }
+
+    // Last block height at which the side to move may still move, for correspondence games
+    pub fn move_deadline(&self) -> Option<u32> {
+        match self.time_control {
+            Some(TimeControl::Blocks { per_move }) if self.status == GameStatus::Active => {
+                Some(self.clock.turn_started_height.saturating_add(per_move))
+            }
+            _ => None,
+        }
+    }
+
+    // Whether the side to move has run out of time at the given block
+    pub fn is_flagged(&self, now_ms: u64, height: u32) -> bool {
+        if self.status != GameStatus::Active {
+            return false;
+        }
+        match self.time_control {
+            Some(TimeControl::Clock { .. }) => {
+                self.remaining_ms(self.game.side_to_move(), now_ms) == Some(0)
+            }
+            Some(TimeControl::Blocks { .. }) => self
+                .move_deadline()
+                .is_some_and(|deadline| height > deadline),
+            None => false,
+        }
+    }
+
+    // Start the clock for both players when the game becomes active
+    fn start_clock(&mut self, now_ms: u64, height: u32) {
+        if let Some(TimeControl::Clock { initial_ms, .. }) = self.time_control {
+            self.clock.white_ms = initial_ms;
+            self.clock.black_ms = initial_ms;
+        }
+        self.clock.turn_started_ms = now_ms;
+        self.clock.turn_started_height = height;
+    }
+
+    // Charge the time used by `mover`, add the increment and restart the turn timer.
+    // Must run before the move is applied, while `mover` is still the side to move.
+    fn switch_clock(&mut self, mover: Color, now_ms: u64, height: u32) {
+        if let Some(TimeControl::Clock { increment_ms, .. }) = self.time_control {
+            let remaining = self.remaining_ms(mover, now_ms).unwrap_or_default();
+            let remaining = remaining.saturating_add(increment_ms);
+            match mover {
+                Color::White => self.clock.white_ms = remaining,
+                Color::Black => self.clock.black_ms = remaining,
+            }
+        }
+        self.clock.turn_started_ms = now_ms;
+        self.clock.turn_started_height = height;
+    }
+
+    fn finish(&mut self, result: Outcome, reason: EndReason, height: u32) {
+        self.status = GameStatus::Finished { result, reason };
+        self.draw_offer = None;
+        self.last_updated = height;
+    }
+}
+
+// Lifecycle of a game: WaitingForOpponent -> Active -> Finished, or Aborted before play starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameStatus {
+    WaitingForOpponent,
+    Active,
+    Finished { result: Outcome, reason: EndReason },
+    Aborted,
+}
+
+// Per-game time control, measured against block timestamps or block heights
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeControl {
+    Clock { initial_ms: u64, increment_ms: u64 }, // Fischer clock
+    Blocks { per_move: u32 },                     // Correspondence: blocks allowed per move
+}
+
+// Clock bookkeeping, only meaningful once the game is active
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ClockState {
+    pub white_ms: u64,        // White's remaining time at the start of the current turn
+    pub black_ms: u64,        // Black's remaining time at the start of the current turn
+    pub turn_started_ms: u64, // Block timestamp at which the current turn started
+    pub turn_started_height: u32, // Block height at which the current turn started
+}
+
+// Amount each player puts into a wagered game's pot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stake {
+    pub asset: String,
+    pub amount: u128,
+}
+
+// Stakes held in the rollup state until `Params::payout_confirmations` blocks have built on
+// the game's result block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Escrow {
+    pub stake: Stake,
+    pub pot: u128,      // One stake before the opponent joins, two after
+    pub released: bool, // Set once the pot has been paid out or refunded
 }
 
+impl Escrow {
+    // Who receives what from the pot: the winner takes it minus the protocol fee, a draw
+    // refunds both stakes
+    fn payouts(
+        &self,
+        result: Outcome,
+        white: PublicKey,
+        black: Option<PublicKey>,
+        params: &Params,
+    ) -> Vec<(PublicKey, u128)> {
+        let Some(black) = black else {
+            return vec![(white, self.pot)];
+        };
+        let fee = match params.fee_recipient {
+            // Split to keep the multiplication from overflowing on large pots
+            Some(_) if result != Outcome::Draw => {
+                let bps = u128::from(params.fee_bps.min(10_000));
+                self.pot / 10_000 * bps + self.pot % 10_000 * bps / 10_000
+            }
+            _ => 0,
+        };
+        let mut payouts = match result {
+            Outcome::WhiteWins => vec![(white, self.pot - fee)],
+            Outcome::BlackWins => vec![(black, self.pot - fee)],
+            Outcome::Draw => vec![
+                (white, self.stake.amount),
+                (black, self.pot - self.stake.amount),
+            ],
+        };
+        if let (Some(recipient), true) = (params.fee_recipient, fee > 0) {
+            payouts.push((recipient, fee));
+        }
+        payouts
+    }
+}
+
+// Protocol parameters, fixed by the genesis file and committed to by the state root.
+// A limit of 0 disables it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Params {
+    pub fee_bps: u16, // Fee taken from decisive wagered pots, in basis points
+    pub fee_recipient: Option<PublicKey>, // No wager fee is taken when unset, tx fees are burned
+    pub fee_asset: String, // Asset transaction fees are paid in
+    pub tx_fee: u128, // Charged to the signer of every verified transaction, even failed ones
+    pub max_txs_per_block: u32, // Sequenced transactions executed per block, deposits excluded
+    pub max_tx_size: u32, // Encoded signed transaction size, in bytes
+    pub max_san_len: u32, // Length of a move string
+    pub max_live_games: u32, // Games waiting for an opponent or being played
+    // Blocks built on a wagered game's result before it pays out. This stands in for the result
+    // being firm: the firm height is not an input of execution, so nodes could not agree on
+    // when it was reached. A result that is rolled back before firm takes its payout with it,
+    // but funds withdrawn in the meantime are only safe if bridges act on firm blocks alone;
+    // set this above the firm lag of the network.
+    pub payout_confirmations: u32,
+    pub deposit_holding_account: Option<PublicKey>, // Credited with deposits to bad destinations
+    pub rollup_id: [u8; 32],                        // Signed over by every transaction
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndReason {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    Agreement,
+    Timeout,
+    Repetition,
+    FiftyMoves,
+}
+
+// Result of executing all transactions of a block
 #[derive(Debug, Clone)]
+pub struct ExecutedBlock {
+    pub hash: Bytes,
+    pub roots: BlockRoots,
+    pub body: BlockBody,
+}
+
+// Commitments folded into a block hash next to the parent hash
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BlockRoots {
+    pub transactions_root: [u8; 32], // sha256 over the applied transactions, in order
+    pub state_root: [u8; 32],        // Merkle root of the state after the block
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GameManager {
-    pub games: HashMap<u32, GameState>, // Map between game index and GameState
-    pub current_block_hash: Bytes,      // Hash of the current block
+    pub games: BTreeMap<u32, GameState>, // Map between game index and GameState, ordered by id
+    pub current_height: u32,             // Height of the block being executed
+    pub current_time_ms: u64,            // Timestamp of the block being executed
+    pub next_game_id: u32,               // Next candidate id for rollup assigned games
+    pub accounts: Accounts,              // Balances credited by deposits
+    pub params: Params,
 }
 
 impl GameManager {
-    // Create a new GameManager
-    pub fn new(starting_hash: Bytes) -> Self {
-        Self {
-            games: HashMap::new(),
-            current_block_hash: starting_hash,
+    // Starts a new game with the signer playing white and adds it to the games map
+    pub fn start_new_game(
+        &mut self,
+        game_id: u32,
+        white: PublicKey,
+        time_control: Option<TimeControl>,
+        stake: Option<Stake>,
+    ) -> Result<(), TxError> {
+        if self.games.contains_key(&game_id) {
+            return Err(TxError::new(ErrorCode::GameExists, "Game already exists"));
         }
-    }
+        // The creator's stake goes into escrow right away
+        let escrow = match stake {
+            Some(stake) => {
+                self.accounts
+                    .debit(&white, &stake.asset, stake.amount)
+                    .map_err(|e| TxError::new(ErrorCode::InsufficientFunds, e))?;
+                Some(Escrow {
+                    pot: stake.amount,
+                    stake,
+                    released: false,
+                })
+            }
+            None => None,
+        };
 
-    // Starts a new game and adds it to the games map
-    pub fn start_new_game(&mut self, game_id: u32) {
         let game = Game::new();
         let game_state = GameState {
             game,
-            winner: None,
+            white,
+            black: None,
+            status: GameStatus::WaitingForOpponent,
+            draw_offer: None,
+            time_control,
+            clock: ClockState::default(),
+            escrow,
             moves: Vec::new(),
+            last_updated: self.current_height,
         };
         self.games.insert(game_id, game_state);
+        Ok(())
     }
 
-    // Attempt to make a move in the specified game
-    pub fn make_move(&mut self, game_id: u32, move_str: &str) -> Result<(), String> {
+    // Starts a new game under the lowest free id at or above the counter
+    pub fn create_game(
+        &mut self,
+        white: PublicKey,
+        time_control: Option<TimeControl>,
+        stake: Option<Stake>,
+    ) -> Result<u32, TxError> {
+        while self.games.contains_key(&self.next_game_id) {
+            self.next_game_id = self.next_game_id.checked_add(1).ok_or("No game ids left")?;
+        }
+        let game_id = self.next_game_id;
+        self.start_new_game(game_id, white, time_control, stake)?;
+        Ok(game_id)
+    }
+
+    // Bind the signer as the black player of an open game
+    pub fn join_game(&mut self, game_id: u32, black: PublicKey) -> Result<(), TxError> {
+        let game_state = self.games.get_mut(&game_id).ok_or_else(game_not_found)?;
+        if game_state.status != GameStatus::WaitingForOpponent {
+            return Err(TxError::new(
+                ErrorCode::InvalidGameState,
+                "Game is not waiting for an opponent",
+            ));
+        }
+        if game_state.white == black {
+            return Err("Cannot join your own game".into());
+        }
+        // Joining a wagered game matches the creator's stake
+        if let Some(escrow) = game_state.escrow.as_mut() {
+            let pot = escrow
+                .pot
+                .checked_add(escrow.stake.amount)
+                .ok_or("Stake too large")?;
+            self.accounts
+                .debit(&black, &escrow.stake.asset, escrow.stake.amount)
+                .map_err(|e| TxError::new(ErrorCode::InsufficientFunds, e))?;
+            escrow.pot = pot;
+        }
+
+        game_state.black = Some(black);
+        game_state.status = GameStatus::Active;
+        game_state.start_clock(self.current_time_ms, self.current_height);
+        game_state.last_updated = self.current_height;
+        Ok(())
+    }
+
+    // Attempt to make a move in the specified game on behalf of `player`
+    pub fn make_move(
+        &mut self,
+        game_id: u32,
+        player: PublicKey,
+        move_str: &str,
+    ) -> Result<(), TxError> {
         // Retrieve the game from the map
-        let game_state = self.games.get_mut(&game_id).ok_or("Game not found")?;
+        let (height, now_ms) = (self.current_height, self.current_time_ms);
+        let game_state = self.active_game_mut(game_id)?;
+
+        // Only the player whose turn it is may move
+        if game_state.player_to_move() != Some(player) {
+            return Err(TxError::new(ErrorCode::NotYourTurn, "Not your turn"));
+        }
 
         // Parse the move
-        let chess_move = match ChessMove::from_san(&game_state.game.current_position(), move_str) {
-            Ok(mv) => mv,
-            Err(_) => return Err("Invalid move".into()),
-        };
+        let chess_move = notation::parse_move(&game_state.game.current_position(), move_str)
+            .map_err(|e| TxError::new(ErrorCode::IllegalMove, e))?;
 
-        // Make the move
+        // Make the move; moving instead of answering declines the opponent's draw offer
+        let mover = game_state.game.side_to_move();
+        game_state.switch_clock(mover, now_ms, height);
         game_state.game.make_move(chess_move);
         game_state.moves.push(chess_move);
+        game_state.last_updated = height;
+        if game_state.draw_offer.is_some_and(|color| color != mover) {
+            game_state.draw_offer = None;
+        }
 
         // Check for game result
         if let Some(result) = game_state.game.result() {
-            game_state.winner = match result {
-                GameResult::WhiteCheckmates | GameResult::BlackResigns => Some(Color::White),
-                GameResult::BlackCheckmates | GameResult::WhiteResigns => Some(Color::Black),
-                GameResult::DrawAccepted | GameResult::Stalemate | GameResult::DrawDeclared => None, // Draw scenarios
+            let (result, reason) = match result {
+                GameResult::WhiteCheckmates => (Outcome::WhiteWins, EndReason::Checkmate),
+                GameResult::BlackCheckmates => (Outcome::BlackWins, EndReason::Checkmate),
+                GameResult::BlackResigns => (Outcome::WhiteWins, EndReason::Resignation),
+                GameResult::WhiteResigns => (Outcome::BlackWins, EndReason::Resignation),
+                GameResult::Stalemate => (Outcome::Draw, EndReason::Stalemate),
+                GameResult::DrawAccepted => (Outcome::Draw, EndReason::Agreement),
+                GameResult::DrawDeclared => (Outcome::Draw, EndReason::Repetition),
             };
+            game_state.finish(result, reason, height);
+        }
+
+        Ok(())
+    }
+
+    // Resign on behalf of `player`, handing the win to the opponent
+    pub fn resign(&mut self, game_id: u32, player: PublicKey) -> Result<(), TxError> {
+        let height = self.current_height;
+        let game_state = self.active_game_mut(game_id)?;
+        let result = match game_state.color_of(player).ok_or_else(not_a_player)? {
+            Color::White => Outcome::BlackWins,
+            Color::Black => Outcome::WhiteWins,
+        };
+        game_state.finish(result, EndReason::Resignation, height);
+        Ok(())
+    }
+
+    // Record a draw offer from `player`; it stands until answered or the opponent moves
+    pub fn offer_draw(&mut self, game_id: u32, player: PublicKey) -> Result<(), TxError> {
+        let height = self.current_height;
+        let game_state = self.active_game_mut(game_id)?;
+        let color = game_state.color_of(player).ok_or_else(not_a_player)?;
+        if game_state.draw_offer.is_some() {
+            return Err("A draw offer is already pending".into());
+        }
+        game_state.draw_offer = Some(color);
+        game_state.last_updated = height;
+        Ok(())
+    }
+
+    // Accept the opponent's pending draw offer
+    pub fn accept_draw(&mut self, game_id: u32, player: PublicKey) -> Result<(), TxError> {
+        let height = self.current_height;
+        let game_state = self.active_game_mut(game_id)?;
+        let color = game_state.color_of(player).ok_or_else(not_a_player)?;
+        match game_state.draw_offer {
+            Some(offered_by) if offered_by != color => {
+                game_state.finish(Outcome::Draw, EndReason::Agreement, height);
+                Ok(())
+            }
+            _ => Err("No draw offer from the opponent".into()),
         }
+    }
 
+    // Decline the opponent's pending draw offer
+    pub fn decline_draw(&mut self, game_id: u32, player: PublicKey) -> Result<(), TxError> {
+        let height = self.current_height;
+        let game_state = self.active_game_mut(game_id)?;
+        let color = game_state.color_of(player).ok_or_else(not_a_player)?;
+        match game_state.draw_offer {
+            Some(offered_by) if offered_by != color => {
+                game_state.draw_offer = None;
+                game_state.last_updated = height;
+                Ok(())
+            }
+            _ => Err("No draw offer from the opponent".into()),
+        }
+    }
+
+    // Claim a draw by threefold repetition or the fifty-move rule
+    pub fn claim_draw(
+        &mut self,
+        game_id: u32,
+        player: PublicKey,
+        claim: DrawClaim,
+    ) -> Result<(), TxError> {
+        let height = self.current_height;
+        let game_state = self.active_game_mut(game_id)?;
+        game_state.color_of(player).ok_or_else(not_a_player)?;
+        let reason = match claim {
+            DrawClaim::Repetition if game_state.repetition_count() >= 3 => EndReason::Repetition,
+            DrawClaim::FiftyMoves if game_state.halfmove_clock() >= 100 => EndReason::FiftyMoves,
+            DrawClaim::Repetition => return Err("Position has not occurred three times".into()),
+            DrawClaim::FiftyMoves => return Err("Fifty-move rule not reached".into()),
+        };
+        game_state.finish(Outcome::Draw, reason, height);
         Ok(())
     }
 
+    // Abort a game before the first move has been played
+    pub fn abort_game(&mut self, game_id: u32, player: PublicKey) -> Result<(), TxError> {
+        let height = self.current_height;
+        let game_state = self.games.get_mut(&game_id).ok_or_else(game_not_found)?;
+        if game_state.is_over() {
+            return Err(TxError::new(
+                ErrorCode::InvalidGameState,
+                "Game is already over",
+            ));
+        }
+        if !game_state.moves.is_empty() {
+            return Err("Game can only be aborted before the first move".into());
+        }
+        game_state.color_of(player).ok_or_else(not_a_player)?;
+        game_state.status = GameStatus::Aborted;
+        game_state.draw_offer = None;
+        game_state.last_updated = height;
+        // Nothing was played, so stakes are returned without waiting for confirmations
+        if let Some(escrow) = game_state.escrow.as_mut() {
+            let mut players = vec![game_state.white];
+            players.extend(game_state.black);
+            for player in players {
+                self.accounts
+                    .credit(&player, &escrow.stake.asset, escrow.stake.amount)?;
+            }
+            escrow.pot = 0;
+            escrow.released = true;
+        }
+        Ok(())
+    }
+
+    // Pay out the pots of wagered games decided at least `payout_confirmations` blocks before
+    // the current block. Only block heights count, so every node releases in the same block,
+    // even if the result block is not yet firm, see `Params::payout_confirmations`.
+    pub fn release_payouts(&mut self) -> Vec<Event> {
+        let height = self.current_height;
+        let confirmations = self.params.payout_confirmations;
+        let mut events = Vec::new();
+        for (game_id, game_state) in self.games.iter_mut() {
+            let GameStatus::Finished { result, .. } = game_state.status else {
+                continue;
+            };
+            if game_state.last_updated.saturating_add(confirmations) > height {
+                continue;
+            }
+            let Some(escrow) = game_state.escrow.as_mut() else {
+                continue;
+            };
+            if escrow.released {
+                continue;
+            }
+            for (account, amount) in
+                escrow.payouts(result, game_state.white, game_state.black, &self.params)
+            {
+                match self.accounts.credit(&account, &escrow.stake.asset, amount) {
+                    Ok(()) => events.push(Event::PayoutReleased {
+                        game_id: *game_id,
+                        account: hex::encode(account),
+                        asset: escrow.stake.asset.clone(),
+                        amount: amount.to_string(),
+                    }),
+                    Err(e) => eprintln!("Failed to pay out game {}: {}", game_id, e),
+                }
+            }
+            escrow.released = true;
+            println!("released pot of game {}", game_id);
+        }
+        events
+    }
+
+    // Seeded games start their clocks with the first block rather than at the genesis
+    // timestamp, which may lie long before the chain starts
+    fn start_seeded_clocks(&mut self) {
+        let (height, now_ms) = (self.current_height, self.current_time_ms);
+        for game_state in self.games.values_mut() {
+            if game_state.status == GameStatus::Active {
+                game_state.clock.turn_started_ms = now_ms;
+                game_state.clock.turn_started_height = height;
+            }
+        }
+    }
+
+    // End every active game whose side to move has run out of time at the current block
+    pub fn flag_expired_games(&mut self) -> Vec<Event> {
+        let (height, now_ms) = (self.current_height, self.current_time_ms);
+        let mut events = Vec::new();
+        for (game_id, game_state) in self.games.iter_mut() {
+            if !game_state.is_flagged(now_ms, height) {
+                continue;
+            }
+            let result = match game_state.game.side_to_move() {
+                Color::White => {
+                    game_state.clock.white_ms = 0;
+                    Outcome::BlackWins
+                }
+                Color::Black => {
+                    game_state.clock.black_ms = 0;
+                    Outcome::WhiteWins
+                }
+            };
+            println!("game {} flagged on time", game_id);
+            game_state.finish(result, EndReason::Timeout, height);
+            events.push(Event::GameEnded {
+                game_id: *game_id,
+                result,
+                reason: EndReason::Timeout,
+            });
+        }
+        events
+    }
+
+    // Look up a game that is currently being played
+    fn active_game_mut(&mut self, game_id: u32) -> Result<&mut GameState, TxError> {
+        let game_state = self.games.get_mut(&game_id).ok_or_else(game_not_found)?;
+        match game_state.status {
+            GameStatus::Active => Ok(game_state),
+            GameStatus::WaitingForOpponent => Err(TxError::new(
+                ErrorCode::InvalidGameState,
+                "Waiting for an opponent to join",
+            )),
+            GameStatus::Finished { .. } | GameStatus::Aborted => Err(TxError::new(
+                ErrorCode::InvalidGameState,
+                "Game is already over",
+            )),
+        }
+    }
+
     // Retrieve the status of a game
     pub fn game_status(&self, game_id: u32) -> Result<&GameState, String> {
         self.games.get(&game_id).ok_or("Game not found".into())
     }
 
+    // Key/value pairs committed to by the state root, sorted by key
+    pub fn state_leaves(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut leaves: Vec<(Vec<u8>, Vec<u8>)> = self
+            .games
+            .iter()
+            .map(|(game_id, game_state)| {
+                (
+                    game_key(*game_id),
+                    serde_json::to_vec(game_state).expect("game state serializes to json"),
+                )
+            })
+            .collect();
+        leaves.push((
+            NEXT_GAME_ID_KEY.to_vec(),
+            self.next_game_id.to_be_bytes().to_vec(),
+        ));
+        leaves.push((
+            PARAMS_KEY.to_vec(),
+            serde_json::to_vec(&self.params).expect("params serialize to json"),
+        ));
+        leaves.extend(self.accounts.state_leaves());
+        leaves.sort_by(|a, b| a.0.cmp(&b.0));
+        leaves
+    }
+
+    // Merkle root over the current state
+    pub fn state_root(&self) -> [u8; 32] {
+        let leaves: Vec<[u8; 32]> = self
+            .state_leaves()
+            .iter()
+            .map(|(key, value)| merkle::leaf_hash(key, value))
+            .collect();
+        merkle::root(&leaves)
+    }
+
+    // Inclusion proof of a game's state against the state root of the block this state
+    // resulted from. `parent_hash` and `roots` are those of that block.
+    pub fn prove_game(
+        &self,
+        game_id: u32,
+        parent_hash: Bytes,
+        roots: BlockRoots,
+    ) -> Result<StateProof, String> {
+        let key = game_key(game_id);
+        let leaves = self.state_leaves();
+        let index = leaves
+            .iter()
+            .position(|(leaf_key, _)| *leaf_key == key)
+            .ok_or("Game not found")?;
+        let leaf_hashes: Vec<[u8; 32]> = leaves
+            .iter()
+            .map(|(key, value)| merkle::leaf_hash(key, value))
+            .collect();
+        if merkle::root(&leaf_hashes) != roots.state_root {
+            return Err("Game state has moved past the requested block".into());
+        }
+        let proof = merkle::prove(&leaf_hashes, index).ok_or("Game not found")?;
+        let (key, value) = leaves.into_iter().nth(index).ok_or("Game not found")?;
+        Ok(StateProof {
+            key,
+            value,
+            proof,
+            parent_hash,
+            height: self.current_height,
+            timestamp_ms: self.current_time_ms,
+            transactions_root: roots.transactions_root,
+        })
+    }
+
+    // Execute a signed transaction, returning the id of the game it created or touched.
+    // Events are appended as they happen, so a failed transaction still reports its fee.
     pub fn execute_transaction(
         &mut self,
-        tx: Transaction,
+        signed_tx: SignedTransaction,
         hasher: &mut Sha256,
-    ) -> Result<(), String> {
-        let tx_bytes = tx.encode();
+        events: &mut Vec<Event>,
+    ) -> Result<Option<u32>, TxError> {
+        signed_tx
+            .verify(&self.params.rollup_id)
+            .map_err(|e| TxError::new(ErrorCode::InvalidSignature, e))?;
+        let tx_bytes = signed_tx.encode();
+        let signer = signed_tx.public_key;
+        self.accounts
+            .check_nonce(&signer, signed_tx.nonce)
+            .map_err(|e| TxError::new(ErrorCode::InvalidNonce, e))?;
+        // Once the fee is paid the transaction is part of the block, whatever happens next
+        events.extend(self.charge_fee(&signer)?);
+        self.accounts.bump_nonce(&signer);
+        hasher.update(&tx_bytes);
+        self.check_limits(&signed_tx.transaction)?;
 
-        match tx {
-            Transaction::StartGame { game_id } => {
-                self.start_new_game(game_id);
+        let touched = signed_tx.transaction.game_id();
+        let status_before = touched
+            .and_then(|game_id| self.games.get(&game_id))
+            .map(|game_state| game_state.status);
+        let game_id = match signed_tx.transaction {
+            Transaction::StartGame {
+                game_id,
+                time_control,
+                stake,
+            } => {
+                self.start_new_game(game_id, signer, time_control, stake)?;
                 println!("starting new game: {}", game_id);
-                hasher.update(&tx_bytes);
-                Ok(())
+                events.push(Event::GameStarted {
+                    game_id,
+                    white: hex::encode(signer),
+                });
+                Some(game_id)
+            }
+            Transaction::CreateGame {
+                time_control,
+                stake,
+            } => {
+                let game_id = self.create_game(signer, time_control, stake)?;
+                println!("starting new game: {}", game_id);
+                events.push(Event::GameStarted {
+                    game_id,
+                    white: hex::encode(signer),
+                });
+                Some(game_id)
+            }
+            Transaction::JoinGame { game_id } => {
+                self.join_game(game_id, signer)?;
+                events.push(Event::GameJoined {
+                    game_id,
+                    black: hex::encode(signer),
+                });
+                Some(game_id)
             }
             Transaction::MakeMove { game_id, move_san } => {
-                let result = self.make_move(game_id, &move_san);
-                if result.is_ok() {
-                    hasher.update(&tx_bytes);
+                self.make_move(game_id, signer, &move_san)?;
+                if let Some(mv) = self.games.get(&game_id).and_then(|g| g.moves.last()) {
+                    events.push(Event::MoveMade {
+                        game_id,
+                        uci: notation::to_uci(*mv),
+                    });
+                }
+                Some(game_id)
+            }
+            Transaction::Resign { game_id } => {
+                self.resign(game_id, signer)?;
+                Some(game_id)
+            }
+            Transaction::OfferDraw { game_id } => {
+                self.offer_draw(game_id, signer)?;
+                events.push(Event::DrawOffered { game_id });
+                Some(game_id)
+            }
+            Transaction::AcceptDraw { game_id } => {
+                self.accept_draw(game_id, signer)?;
+                Some(game_id)
+            }
+            Transaction::DeclineDraw { game_id } => {
+                self.decline_draw(game_id, signer)?;
+                events.push(Event::DrawDeclined { game_id });
+                Some(game_id)
+            }
+            Transaction::ClaimDraw { game_id, claim } => {
+                self.claim_draw(game_id, signer, claim)?;
+                Some(game_id)
+            }
+            Transaction::Abort { game_id } => {
+                self.abort_game(game_id, signer)?;
+                Some(game_id)
+            }
+            Transaction::Withdraw {
+                asset,
+                amount,
+                destination,
+                memo,
+            } => {
+                let id = self.accounts.withdraw(
+                    &signer,
+                    &asset,
+                    amount,
+                    &destination,
+                    &memo,
+                    self.current_height,
+                )?;
+                println!("withdrawal {}: {} {} to {}", id, amount, asset, destination);
+                events.push(Event::WithdrawalRequested {
+                    withdrawal_id: id,
+                    asset,
+                    amount: amount.to_string(),
+                    destination,
+                });
+                None
+            }
+        };
+
+        // Report games this transaction brought to an end
+        let status_after = touched
+            .and_then(|game_id| self.games.get(&game_id))
+            .map(|game_state| game_state.status);
+        if let (Some(game_id), true) = (touched, status_after != status_before) {
+            match status_after {
+                Some(GameStatus::Finished { result, reason }) => events.push(Event::GameEnded {
+                    game_id,
+                    result,
+                    reason,
+                }),
+                Some(GameStatus::Aborted) => events.push(Event::GameAborted { game_id }),
+                _ => {}
+            }
+        }
+        Ok(game_id)
+    }
+
+    // Take the transaction fee from the signer's balance
+    fn charge_fee(&mut self, payer: &PublicKey) -> Result<Option<Event>, TxError> {
+        let fee = self.params.tx_fee;
+        if fee == 0 {
+            return Ok(None);
+        }
+        self.accounts
+            .debit(payer, &self.params.fee_asset, fee)
+            .map_err(|e| {
+                TxError::new(
+                    ErrorCode::InsufficientFunds,
+                    format!("Cannot pay transaction fee: {}", e),
+                )
+            })?;
+        if let Some(recipient) = self.params.fee_recipient {
+            self.accounts
+                .credit(&recipient, &self.params.fee_asset, fee)?;
+        }
+        Ok(Some(Event::FeeCharged {
+            account: hex::encode(payer),
+            asset: self.params.fee_asset.clone(),
+            amount: fee.to_string(),
+        }))
+    }
+
+    // Enforce the per-transaction resource limits
+    fn check_limits(&self, transaction: &Transaction) -> Result<(), TxError> {
+        match transaction {
+            Transaction::MakeMove { move_san, .. }
+                if !within_limit(self.params.max_san_len, move_san.len()) =>
+            {
+                Err(TxError::new(
+                    ErrorCode::LimitExceeded,
+                    format!("Move longer than {} bytes", self.params.max_san_len),
+                ))
+            }
+            Transaction::StartGame { .. } | Transaction::CreateGame { .. } => {
+                let live_games = self.games.values().filter(|game| !game.is_over()).count();
+                if within_limit(self.params.max_live_games, live_games + 1) {
+                    Ok(())
+                } else {
+                    Err(TxError::new(
+                        ErrorCode::LimitExceeded,
+                        format!(
+                            "Too many live games, the limit is {}",
+                            self.params.max_live_games
+                        ),
+                    ))
                 }
-                result
             }
+            _ => Ok(()),
         }
     }
 
-    pub fn process_transactions(&mut self, data: &Vec<Bytes>, current_hash: Bytes) -> Bytes {
+    pub fn process_transactions(
+        &mut self,
+        inputs: &[RollupInput],
+        current_hash: Bytes,
+        height: u32,
+        timestamp_ms: u64,
+    ) -> ExecutedBlock {
+        self.current_height = height;
+        self.current_time_ms = timestamp_ms;
+        // Pots of games decided enough blocks ago become spendable
+        let mut block_events = self.release_payouts();
+        // Every game present before the first block was seeded at genesis
+        if height == 1 {
+            self.start_seeded_clocks();
+        }
+        // Time runs out at block boundaries, before any transaction of the block applies
+        block_events.extend(self.flag_expired_games());
         let mut hasher = Sha256::new();
-        hasher.update(&current_hash);
-        for encoded_tx in data {
-            match Transaction::decode(encoded_tx.to_owned()) {
-                Ok(tx) => {
-                    if let Err(e) = self.execute_transaction(tx, &mut hasher) {
-                        eprintln!("Failed to execute transaction: {}", e);
+        let mut transactions = Vec::with_capacity(inputs.len());
+        let mut receipts = Vec::with_capacity(inputs.len());
+        let mut sequenced = 0;
+        for (index, input) in inputs.iter().enumerate() {
+            let mut events = Vec::new();
+            let mut decoded = None;
+            let (encoded, over_limit, result) = match input {
+                RollupInput::Transaction(encoded_tx) => {
+                    sequenced += 1;
+                    // Over-limit transactions are rejected before any decoding work
+                    let limit_error = if !within_limit(self.params.max_txs_per_block, sequenced) {
+                        Some(TxError::new(
+                            ErrorCode::LimitExceeded,
+                            "Block transaction limit reached",
+                        ))
+                    } else if !within_limit(self.params.max_tx_size, encoded_tx.len()) {
+                        Some(TxError::new(
+                            ErrorCode::LimitExceeded,
+                            format!("Transaction larger than {} bytes", self.params.max_tx_size),
+                        ))
+                    } else {
+                        None
+                    };
+                    let over_limit = limit_error.is_some();
+                    let result = match limit_error {
+                        Some(e) => Err(e),
+                        None => match SignedTransaction::decode(encoded_tx.to_owned()) {
+                            Ok(tx) => {
+                                decoded = Some(DecodedInput::Transaction {
+                                    signer: hex::encode(tx.public_key),
+                                    nonce: tx.nonce,
+                                    transaction: tx.transaction.clone(),
+                                });
+                                self.execute_transaction(tx, &mut hasher, &mut events)
+                            }
+                            Err(e) => Err(TxError::new(ErrorCode::DecodeFailed, e)),
+                        },
+                    };
+                    (encoded_tx.clone(), over_limit, result)
+                }
+                RollupInput::Deposit(deposit) => {
+                    let encoded_deposit = deposit.encode();
+                    decoded = Some(DecodedInput::Deposit {
+                        destination: deposit.destination.clone(),
+                        asset: deposit.asset.clone(),
+                        amount: deposit.amount.to_string(),
+                        source_transaction_id: deposit.source_transaction_id.clone(),
+                        source_action_index: deposit.source_action_index,
+                    });
+                    let result = self
+                        .accounts
+                        .apply_deposit(deposit, self.params.deposit_holding_account)
+                        .map_err(|e| TxError::new(ErrorCode::InvalidDeposit, e));
+                    if let Ok(held) = result {
+                        hasher.update(&encoded_deposit);
+                        let (asset, amount) = (deposit.asset.clone(), deposit.amount.to_string());
+                        match held {
+                            None => {
+                                println!(
+                                    "deposited {} {} to {}",
+                                    deposit.amount, deposit.asset, deposit.destination
+                                );
+                                events.push(Event::Deposited {
+                                    account: deposit.destination.clone(),
+                                    asset,
+                                    amount,
+                                });
+                            }
+                            // Not a rollup account: the holding account keeps the funds
+                            Some(holding_account) => {
+                                println!(
+                                    "held deposit of {} {} to {}",
+                                    deposit.amount, deposit.asset, deposit.destination
+                                );
+                                events.push(Event::DepositHeld {
+                                    destination: deposit.destination.clone(),
+                                    account: hex::encode(holding_account),
+                                    asset,
+                                    amount,
+                                });
+                            }
+                        }
                     }
+                    (encoded_deposit, false, result.map(|_| None))
                 }
-                Err(e) => eprintln!("Failed to decode transaction: {}", e),
+            };
+            if let Err(e) = &result {
+                eprintln!("Rejected rollup data {} of block {}: {}", index, height, e);
             }
+            let tx_hash = transaction_hash(&encoded);
+            // Only the hash and size of over-limit data are kept, so it cannot bloat storage
+            transactions.push(BlockTransaction {
+                index: index as u32,
+                size: encoded.len() as u32,
+                raw: (!over_limit).then_some(encoded),
+                decoded,
+            });
+            receipts.push(Receipt {
+                tx_hash,
+                height,
+                index: index as u32,
+                game_id: result.as_ref().ok().copied().flatten(),
+                error: result.err(),
+                events,
+            });
+        }
+        let roots = BlockRoots {
+            transactions_root: hasher.finalize().into(),
+            state_root: self.state_root(),
+        };
+        ExecutedBlock {
+            hash: block_hash(&current_hash, height, timestamp_ms, &roots),
+            roots,
+            body: BlockBody {
+                transactions,
+                events: block_events,
+                receipts,
+            },
         }
-        hasher.finalize().into_iter().collect()
+    }
+}
+
+// Rollup data of a block, in sequencer order
+#[derive(Debug, Clone)]
+pub enum RollupInput {
+    Transaction(Bytes),
+    Deposit(Deposit),
+}
+
+fn game_not_found() -> TxError {
+    TxError::new(ErrorCode::GameNotFound, "Game not found")
+}
+
+fn not_a_player() -> TxError {
+    TxError::new(ErrorCode::NotAPlayer, "Not a player in this game")
+}
+
+// Limits of 0 are disabled
+fn within_limit(limit: u32, value: usize) -> bool {
+    limit == 0 || value <= limit as usize
+}
+
+// Proof that a state entry is committed to by a block hash: the leaf is folded into the state
+// root, which together with the rest of the header must hash to the block hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateProof {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>, // JSON encoded entry, a `GameState` for game keys
+    pub proof: merkle::MerkleProof,
+    pub parent_hash: Bytes,
+    pub height: u32,
+    pub timestamp_ms: u64,
+    pub transactions_root: [u8; 32],
+}
+
+impl StateProof {
+    // State root implied by the proof
+    pub fn state_root(&self) -> Result<[u8; 32], String> {
+        self.proof
+            .root(merkle::leaf_hash(&self.key, &self.value))
+            .ok_or("Malformed proof".into())
+    }
+
+    // Check the proof against a block hash, e.g. one taken from `AppState::get_block`
+    pub fn verify(&self, expected_hash: &[u8]) -> Result<(), String> {
+        let roots = BlockRoots {
+            transactions_root: self.transactions_root,
+            state_root: self.state_root()?,
+        };
+        if block_hash(&self.parent_hash, self.height, self.timestamp_ms, &roots).as_ref()
+            != expected_hash
+        {
+            return Err("Proof does not match block hash".into());
+        }
+        Ok(())
     }
 
-    pub fn finalize_block_hash(&mut self, hasher: Sha256) -> Bytes {
-        // Finalize the block hash and save it as the current block's hash
-        let final_hash = hasher.finalize();
-        let final_hash_bytes = Bytes::copy_from_slice(&final_hash);
-        self.current_block_hash = final_hash_bytes.clone();
-        final_hash_bytes
+    // Check that the proof commits the block hash to the state of `game_id` and return that
+    // state. Bridges and light clients must use this rather than `verify`, which accepts a
+    // proof of any leaf, e.g. another game or a balance.
+    pub fn verify_game(&self, game_id: u32, expected_hash: &[u8]) -> Result<GameState, String> {
+        if self.key != game_key(game_id) {
+            return Err("Proof is not for the requested game".into());
+        }
+        self.verify(expected_hash)?;
+        serde_json::from_slice(&self.value).map_err(|e| format!("Malformed game state: {}", e))
     }
 }
 
-impl Transaction {
-    // Encode the transaction into bytes
-    pub fn encode(&self) -> Bytes {
-        let mut buffer = BytesMut::new();
+// Block hash: sha256 over the parent hash, the height, the timestamp in milliseconds as seen
+// by execution, the transactions root and the state root
+pub fn block_hash(parent_hash: &[u8], height: u32, timestamp_ms: u64, roots: &BlockRoots) -> Bytes {
+    let mut hasher = Sha256::new();
+    hasher.update(parent_hash);
+    hasher.update(height.to_be_bytes());
+    hasher.update(timestamp_ms.to_be_bytes());
+    hasher.update(roots.transactions_root);
+    hasher.update(roots.state_root);
+    Bytes::copy_from_slice(&hasher.finalize())
+}
 
-        match self {
-            Transaction::StartGame { game_id } => {
-                buffer.put_u8(0); // Indicator for StartGame
-                buffer.put_u32(*game_id);
-            }
-            Transaction::MakeMove { game_id, move_san } => {
-                buffer.put_u8(1); // Indicator for MakeMove
-                buffer.put_u32(*game_id);
-                buffer.put_u32(move_san.len() as u32);
-                buffer.put_slice(move_san.as_bytes());
+// Block timestamp in milliseconds, the resolution games are timed at
+pub fn timestamp_ms(timestamp: &pbjson_types::Timestamp) -> u64 {
+    (timestamp.seconds.max(0) as u64) * 1000 + (timestamp.nanos.max(0) as u64) / 1_000_000
+}
+
+// State keys are a prefix followed by the big-endian id of the entry
+const GAME_KEY_PREFIX: &[u8] = b"game/";
+const NEXT_GAME_ID_KEY: &[u8] = b"meta/next_game_id";
+const PARAMS_KEY: &[u8] = b"meta/params";
+
+fn state_key(prefix: &[u8], id: &[u8]) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend_from_slice(id);
+    key
+}
+
+// State key of a game, the key a game proof must carry
+pub fn game_key(game_id: u32) -> Vec<u8> {
+    state_key(GAME_KEY_PREFIX, &game_id.to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astria_core::crypto::SigningKey;
+
+    fn wagered(amount: u128, fee_bps: u16) -> (Escrow, Params) {
+        let escrow = Escrow {
+            stake: Stake {
+                asset: "nria".into(),
+                amount,
+            },
+            pot: amount * 2,
+            released: false,
+        };
+        let params = Params {
+            fee_bps,
+            fee_recipient: Some([9u8; 32]),
+            ..Params::default()
+        };
+        (escrow, params)
+    }
+
+    #[test]
+    fn protocol_fee_rounds_down() {
+        let (white, black) = ([1u8; 32], [2u8; 32]);
+        // 250 bps of 20002 is 500.05
+        let (escrow, params) = wagered(10_001, 250);
+        assert_eq!(
+            escrow.payouts(Outcome::BlackWins, white, Some(black), &params),
+            vec![(black, 19_502), ([9u8; 32], 500)]
+        );
+        // Below one unit of fee nothing is taken
+        let (escrow, params) = wagered(1, 250);
+        assert_eq!(
+            escrow.payouts(Outcome::WhiteWins, white, Some(black), &params),
+            vec![(white, 2)]
+        );
+    }
+
+    #[test]
+    fn protocol_fee_does_not_overflow_large_pots() {
+        let (white, black) = ([1u8; 32], [2u8; 32]);
+        let (escrow, params) = wagered(u128::MAX / 2, 10_000);
+        let pot = escrow.pot;
+        assert_eq!(
+            escrow.payouts(Outcome::WhiteWins, white, Some(black), &params),
+            vec![(white, 0), ([9u8; 32], pot)]
+        );
+        let (escrow, params) = wagered(u128::MAX / 2, 1);
+        let fee = pot / 10_000;
+        assert_eq!(
+            escrow.payouts(Outcome::WhiteWins, white, Some(black), &params),
+            vec![(white, pot - fee), ([9u8; 32], fee)]
+        );
+    }
+
+    #[test]
+    fn draws_refund_stakes_without_fee() {
+        let (white, black) = ([1u8; 32], [2u8; 32]);
+        let (escrow, params) = wagered(10_001, 250);
+        assert_eq!(
+            escrow.payouts(Outcome::Draw, white, Some(black), &params),
+            vec![(white, 10_001), (black, 10_001)]
+        );
+        // Nobody joined: the creator gets the stake back
+        let escrow = Escrow {
+            pot: 10_001,
+            ..escrow
+        };
+        assert_eq!(
+            escrow.payouts(Outcome::WhiteWins, white, None, &params),
+            vec![(white, 10_001)]
+        );
+    }
+
+    #[test]
+    fn seeded_clocks_start_with_the_first_block() {
+        let (white, black) = ([1u8; 32], [2u8; 32]);
+        let clock = TimeControl::Clock {
+            initial_ms: 60_000,
+            increment_ms: 0,
+        };
+        // Seeded at a genesis timestamp of 0, the first block comes a day later
+        let mut game_manager = GameManager::default();
+        game_manager
+            .start_new_game(1, white, Some(clock), None)
+            .unwrap();
+        game_manager.join_game(1, black).unwrap();
+        let day_ms = 86_400_000;
+        game_manager.process_transactions(&[], Bytes::new(), 1, day_ms);
+        let game_state = game_manager.game_status(1).unwrap();
+        assert_eq!(game_state.status, GameStatus::Active);
+        assert_eq!(game_state.remaining_ms(Color::White, day_ms), Some(60_000));
+        // From then on the clock runs as usual
+        game_manager.process_transactions(&[], Bytes::new(), 2, day_ms + 60_000);
+        let game_state = game_manager.game_status(1).unwrap();
+        assert_eq!(
+            game_state.status,
+            GameStatus::Finished {
+                result: Outcome::BlackWins,
+                reason: EndReason::Timeout
             }
-        }
+        );
+    }
+
+    #[test]
+    fn game_proofs_only_verify_for_their_game() {
+        let mut game_manager = GameManager::default();
+        game_manager
+            .start_new_game(1, [1u8; 32], None, None)
+            .unwrap();
+        game_manager
+            .start_new_game(2, [2u8; 32], None, None)
+            .unwrap();
+        let roots = BlockRoots {
+            transactions_root: [0u8; 32],
+            state_root: game_manager.state_root(),
+        };
+        let hash = block_hash(&[], 0, 0, &roots);
+        let proof = game_manager.prove_game(1, Bytes::new(), roots).unwrap();
+        let game_state = proof.verify_game(1, &hash).unwrap();
+        assert_eq!(game_state.white, [1u8; 32]);
+        assert!(proof.verify_game(2, &hash).is_err());
+        // A leaf that is not a game passes `verify` but not `verify_game`
+        let leaves = game_manager.state_leaves();
+        let index = leaves
+            .iter()
+            .position(|(key, _)| key.as_slice() == NEXT_GAME_ID_KEY)
+            .unwrap();
+        let leaf_hashes: Vec<[u8; 32]> = leaves
+            .iter()
+            .map(|(key, value)| merkle::leaf_hash(key, value))
+            .collect();
+        let (key, value) = leaves[index].clone();
+        let forged = StateProof {
+            key,
+            value,
+            proof: merkle::prove(&leaf_hashes, index).unwrap(),
+            ..proof
+        };
+        assert!(forged.verify(&hash).is_ok());
+        assert!(forged.verify_game(1, &hash).is_err());
+    }
+
+    // Encoded transaction signed by the key derived from `seed`, for the default rollup id
+    fn signed(seed: u8, nonce: u64, transaction: Transaction) -> RollupInput {
+        let signing_key = SigningKey::from([seed; 32]);
+        let signature = signing_key.sign(&SignedTransaction::signing_bytes(
+            &Params::default().rollup_id,
+            nonce,
+            &transaction,
+        ));
+        let signed_tx = SignedTransaction {
+            public_key: signing_key.verification_key().to_bytes(),
+            signature: signature.to_bytes(),
+            nonce,
+            transaction,
+        };
+        RollupInput::Transaction(signed_tx.encode())
+    }
 
-        buffer.freeze()
+    fn error_code(executed: &ExecutedBlock, index: usize) -> Option<ErrorCode> {
+        executed.body.receipts[index]
+            .error
+            .as_ref()
+            .map(|error| error.code)
     }
 
-    // Decode bytes back into a transaction
-    pub fn decode(mut data: Bytes) -> Result<Self, String> {
-        if data.remaining() < 5 {
-            return Err("Data too short".into());
+    #[test]
+    fn replayed_transactions_are_rejected() {
+        let mut game_manager = GameManager::default();
+        let input = signed(
+            1,
+            0,
+            Transaction::StartGame {
+                game_id: 1,
+                time_control: None,
+                stake: None,
+            },
+        );
+        let executed = game_manager.process_transactions(&[input.clone()], Bytes::new(), 1, 0);
+        assert_eq!(error_code(&executed, 0), None);
+        // The same signed transaction sequenced again in a later block
+        let executed = game_manager.process_transactions(&[input], executed.hash, 2, 0);
+        assert_eq!(error_code(&executed, 0), Some(ErrorCode::InvalidNonce));
+        // Nonce 0 is used up for every transaction of the signer
+        let other = signed(1, 0, Transaction::JoinGame { game_id: 1 });
+        let executed = game_manager.process_transactions(&[other], executed.hash, 3, 0);
+        assert_eq!(error_code(&executed, 0), Some(ErrorCode::InvalidNonce));
+    }
+
+    #[test]
+    fn failed_transactions_that_paid_their_fee_use_up_their_nonce() {
+        let signer = SigningKey::from([1u8; 32]).verification_key().to_bytes();
+        let mut game_manager = GameManager::default();
+        game_manager.params.tx_fee = 1;
+        game_manager.params.fee_asset = "nria".into();
+        game_manager.accounts.credit(&signer, "nria", 10).unwrap();
+        let input = signed(1, 0, Transaction::JoinGame { game_id: 7 });
+        let executed = game_manager.process_transactions(&[input], Bytes::new(), 1, 0);
+        assert_eq!(error_code(&executed, 0), Some(ErrorCode::GameNotFound));
+        assert_eq!(game_manager.accounts.balance(&signer, "nria"), 9);
+        assert_eq!(game_manager.accounts.next_nonce(&signer), 1);
+    }
+
+    const WHITE: PublicKey = [1u8; 32];
+    const BLACK: PublicKey = [2u8; 32];
+    const OTHER: PublicKey = [3u8; 32];
+
+    // Friendly game 1 between WHITE and BLACK, with the given moves played alternately
+    fn game_after(moves: &[&str]) -> GameManager {
+        let mut game_manager = GameManager::default();
+        game_manager.start_new_game(1, WHITE, None, None).unwrap();
+        game_manager.join_game(1, BLACK).unwrap();
+        for move_str in moves {
+            let player = game_manager.games[&1].player_to_move().unwrap();
+            game_manager.make_move(1, player, move_str).unwrap();
         }
+        game_manager
+    }
 
-        let tx_type = data.get_u8();
-        let game_id = data.get_u32();
+    fn code(result: Result<(), TxError>) -> ErrorCode {
+        result.unwrap_err().code
+    }
 
-        match tx_type {
-            0 => Ok(Transaction::StartGame { game_id }),
-            1 => {
-                if data.remaining() < 4 {
-                    return Err("Data too short for move length".into());
-                }
-                let move_len = data.get_u32() as usize;
+    #[test]
+    fn games_are_joined_once_by_another_player() {
+        let mut game_manager = GameManager::default();
+        game_manager.start_new_game(1, WHITE, None, None).unwrap();
+        assert_eq!(
+            code(game_manager.make_move(1, WHITE, "e4")),
+            ErrorCode::InvalidGameState
+        );
+        assert_eq!(code(game_manager.join_game(1, WHITE)), ErrorCode::Rejected);
+        assert_eq!(
+            code(game_manager.join_game(2, BLACK)),
+            ErrorCode::GameNotFound
+        );
+        game_manager.join_game(1, BLACK).unwrap();
+        assert_eq!(game_manager.games[&1].status, GameStatus::Active);
+        assert_eq!(game_manager.games[&1].black, Some(BLACK));
+        assert_eq!(
+            code(game_manager.join_game(1, OTHER)),
+            ErrorCode::InvalidGameState
+        );
+        assert_eq!(
+            code(game_manager.start_new_game(1, OTHER, None, None)),
+            ErrorCode::GameExists
+        );
+    }
 
-                if data.remaining() < move_len {
-                    return Err("Data too short for move".into());
-                }
+    #[test]
+    fn only_the_player_to_move_may_move() {
+        let mut game_manager = game_after(&[]);
+        assert_eq!(
+            code(game_manager.make_move(1, BLACK, "e5")),
+            ErrorCode::NotYourTurn
+        );
+        assert_eq!(
+            code(game_manager.make_move(1, OTHER, "e4")),
+            ErrorCode::NotYourTurn
+        );
+        assert_eq!(
+            code(game_manager.make_move(1, WHITE, "e5")),
+            ErrorCode::IllegalMove
+        );
+        game_manager.make_move(1, WHITE, "e4").unwrap();
+        assert_eq!(
+            code(game_manager.make_move(1, WHITE, "d4")),
+            ErrorCode::NotYourTurn
+        );
+        game_manager.make_move(1, BLACK, "e7e5").unwrap();
+        assert_eq!(game_manager.games[&1].moves.len(), 2);
+    }
 
-                let move_san = String::from_utf8(data.split_to(move_len).to_vec())
-                    .map_err(|_| "Invalid UTF-8 in move string")?;
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut game_manager = game_after(&["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(
+            game_manager.games[&1].status,
+            GameStatus::Finished {
+                result: Outcome::BlackWins,
+                reason: EndReason::Checkmate
+            }
+        );
+        assert_eq!(game_manager.games[&1].winner(), Some(Color::Black));
+        assert_eq!(
+            code(game_manager.make_move(1, WHITE, "e2e3")),
+            ErrorCode::InvalidGameState
+        );
+    }
 
-                Ok(Transaction::MakeMove { game_id, move_san })
+    #[test]
+    fn stalemate_ends_the_game_in_a_draw() {
+        // Sam Loyd's ten move stalemate
+        let mut game_manager = game_after(&[
+            "e2e3", "a7a5", "d1h5", "a8a6", "h5a5", "h7h5", "h2h4", "a6h6", "a5c7", "f7f6", "c7d7",
+            "e8f7", "d7b7", "d8d3", "b7b8", "d3h7", "b8c8", "f7g6", "c8e6",
+        ]);
+        assert_eq!(
+            game_manager.games[&1].status,
+            GameStatus::Finished {
+                result: Outcome::Draw,
+                reason: EndReason::Stalemate
             }
-            _ => Err("Unknown transaction type".into()),
-        }
+        );
+        assert_eq!(
+            code(game_manager.make_move(1, BLACK, "h7h8")),
+            ErrorCode::InvalidGameState
+        );
+    }
+
+    // Knights out and back, returning to the starting position every four half-moves
+    const KNIGHT_SHUFFLE: [&str; 4] = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    #[test]
+    fn resigning_hands_the_win_to_the_opponent() {
+        let mut game_manager = game_after(&["e2e4"]);
+        assert_eq!(code(game_manager.resign(1, OTHER)), ErrorCode::NotAPlayer);
+        game_manager.resign(1, BLACK).unwrap();
+        assert_eq!(
+            game_manager.games[&1].status,
+            GameStatus::Finished {
+                result: Outcome::WhiteWins,
+                reason: EndReason::Resignation
+            }
+        );
+        assert_eq!(
+            code(game_manager.resign(1, WHITE)),
+            ErrorCode::InvalidGameState
+        );
+    }
+
+    #[test]
+    fn draw_offers_are_answered_by_the_opponent() {
+        let mut game_manager = game_after(&[]);
+        assert_eq!(
+            code(game_manager.offer_draw(1, OTHER)),
+            ErrorCode::NotAPlayer
+        );
+        game_manager.offer_draw(1, WHITE).unwrap();
+        assert_eq!(code(game_manager.offer_draw(1, BLACK)), ErrorCode::Rejected);
+        assert_eq!(
+            code(game_manager.accept_draw(1, WHITE)),
+            ErrorCode::Rejected
+        );
+        game_manager.decline_draw(1, BLACK).unwrap();
+        assert_eq!(game_manager.games[&1].draw_offer, None);
+        assert_eq!(
+            code(game_manager.accept_draw(1, BLACK)),
+            ErrorCode::Rejected
+        );
+
+        // Moving instead of answering declines the offer
+        game_manager.offer_draw(1, WHITE).unwrap();
+        game_manager.make_move(1, WHITE, "e2e4").unwrap();
+        assert_eq!(game_manager.games[&1].draw_offer, Some(Color::White));
+        game_manager.make_move(1, BLACK, "e7e5").unwrap();
+        assert_eq!(game_manager.games[&1].draw_offer, None);
+
+        game_manager.offer_draw(1, BLACK).unwrap();
+        game_manager.accept_draw(1, WHITE).unwrap();
+        assert_eq!(
+            game_manager.games[&1].status,
+            GameStatus::Finished {
+                result: Outcome::Draw,
+                reason: EndReason::Agreement
+            }
+        );
+        assert_eq!(
+            code(game_manager.offer_draw(1, WHITE)),
+            ErrorCode::InvalidGameState
+        );
+    }
+
+    #[test]
+    fn repetition_can_be_claimed_on_the_third_occurrence() {
+        let moves: Vec<&str> = KNIGHT_SHUFFLE.iter().cycle().take(7).copied().collect();
+        let mut game_manager = game_after(&moves);
+        assert_eq!(
+            code(game_manager.claim_draw(1, WHITE, DrawClaim::Repetition)),
+            ErrorCode::Rejected
+        );
+        game_manager.make_move(1, BLACK, "f6g8").unwrap();
+        assert_eq!(game_manager.games[&1].repetition_count(), 3);
+        assert_eq!(
+            code(game_manager.claim_draw(1, OTHER, DrawClaim::Repetition)),
+            ErrorCode::NotAPlayer
+        );
+        game_manager
+            .claim_draw(1, BLACK, DrawClaim::Repetition)
+            .unwrap();
+        assert_eq!(
+            game_manager.games[&1].status,
+            GameStatus::Finished {
+                result: Outcome::Draw,
+                reason: EndReason::Repetition
+            }
+        );
+    }
+
+    #[test]
+    fn fifty_moves_can_be_claimed_after_a_hundred_quiet_half_moves() {
+        let mut moves: Vec<&str> = vec!["e2e4", "e7e5"];
+        moves.extend(KNIGHT_SHUFFLE.iter().cycle().take(99));
+        let mut game_manager = game_after(&moves);
+        assert_eq!(game_manager.games[&1].halfmove_clock(), 99);
+        assert_eq!(
+            code(game_manager.claim_draw(1, BLACK, DrawClaim::FiftyMoves)),
+            ErrorCode::Rejected
+        );
+        game_manager.make_move(1, BLACK, "f6g8").unwrap();
+        game_manager
+            .claim_draw(1, WHITE, DrawClaim::FiftyMoves)
+            .unwrap();
+        assert_eq!(
+            game_manager.games[&1].status,
+            GameStatus::Finished {
+                result: Outcome::Draw,
+                reason: EndReason::FiftyMoves
+            }
+        );
+    }
+
+    #[test]
+    fn games_can_only_be_aborted_before_the_first_move() {
+        let mut game_manager = GameManager::default();
+        game_manager.start_new_game(1, WHITE, None, None).unwrap();
+        assert_eq!(
+            code(game_manager.abort_game(1, BLACK)),
+            ErrorCode::NotAPlayer
+        );
+        game_manager.abort_game(1, WHITE).unwrap();
+        assert_eq!(game_manager.games[&1].status, GameStatus::Aborted);
+        assert_eq!(
+            code(game_manager.abort_game(1, WHITE)),
+            ErrorCode::InvalidGameState
+        );
+
+        let mut game_manager = game_after(&[]);
+        game_manager.abort_game(1, BLACK).unwrap();
+        assert_eq!(game_manager.games[&1].status, GameStatus::Aborted);
+
+        let mut game_manager = game_after(&["e2e4"]);
+        assert_eq!(code(game_manager.abort_game(1, BLACK)), ErrorCode::Rejected);
+        assert_eq!(game_manager.games[&1].status, GameStatus::Active);
     }
 }
@@ -0,0 +1,260 @@
+use crate::game::{GameManager, Params, PublicKey, TimeControl};
+use crate::rollup_app::AppState;
+use astria_core::generated::execution::v1 as execution;
+use astria_core::primitive::v1::RollupId;
+use astria_core::Protobuf;
+use bytes::Bytes;
+use color_eyre::eyre::{self, eyre, WrapErr as _};
+use serde::Deserialize;
+use std::path::Path;
+
+// Chain identity and initial state, read from a JSON or TOML file named by `genesis_filepath`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Genesis {
+    pub rollup_name: String, // The rollup id is derived from it
+    pub sequencer_genesis_block_height: u32,
+    pub celestia_block_variance: u64,
+    pub base_celestia_height: u64, // Celestia height the first commitment state starts from
+    pub block_hash: String,        // hex encoded hash of block 0
+    #[serde(default)]
+    pub timestamp_seconds: i64, // Timestamp of block 0
+    #[serde(default)]
+    pub params: GenesisParams,
+    #[serde(default)]
+    pub balances: Vec<GenesisBalance>,
+    #[serde(default)]
+    pub games: Vec<GenesisGame>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisBalance {
+    pub account: String, // hex encoded public key
+    pub asset: String,
+    pub amount: String, // decimal string, amounts may exceed 64 bits
+}
+
+// Protocol parameters, committed to by the state root so every node runs with the same ones.
+// A limit of 0 disables it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisParams {
+    #[serde(default)]
+    pub protocol_fee_bps: u16, // Fee taken from decisive wagered pots, in basis points
+    #[serde(default)]
+    pub protocol_fee_recipient: String, // hex encoded public key, no fee is taken when empty
+    #[serde(default)]
+    pub fee_asset: String, // Asset transaction fees are paid in
+    #[serde(default = "default_tx_fee")]
+    pub tx_fee: String, // decimal string, charged for every transaction
+    #[serde(default = "default_max_txs_per_block")]
+    pub max_txs_per_block: u32,
+    #[serde(default = "default_max_tx_size")]
+    pub max_tx_size: u32, // in bytes
+    #[serde(default = "default_max_san_len")]
+    pub max_san_len: u32,
+    #[serde(default = "default_max_live_games")]
+    pub max_live_games: u32,
+    // Blocks a decided wagered game waits before paying out. Soft blocks, not firmness, are
+    // counted, so keep this well above how far the firm height lags behind soft
+    #[serde(default = "default_payout_confirmations")]
+    pub payout_confirmations: u32,
+    // hex encoded public key credited with deposits whose destination is not a rollup account;
+    // without one such deposits are rejected and stay locked on the sequencer
+    #[serde(default)]
+    pub deposit_holding_account: String,
+}
+
+impl Default for GenesisParams {
+    fn default() -> Self {
+        GenesisParams {
+            protocol_fee_bps: 0,
+            protocol_fee_recipient: String::new(),
+            fee_asset: String::new(),
+            tx_fee: default_tx_fee(),
+            max_txs_per_block: default_max_txs_per_block(),
+            max_tx_size: default_max_tx_size(),
+            max_san_len: default_max_san_len(),
+            max_live_games: default_max_live_games(),
+            payout_confirmations: default_payout_confirmations(),
+            deposit_holding_account: String::new(),
+        }
+    }
+}
+
+fn default_tx_fee() -> String {
+    "0".to_string()
+}
+
+fn default_max_txs_per_block() -> u32 {
+    1000
+}
+
+fn default_max_tx_size() -> u32 {
+    4096
+}
+
+fn default_max_san_len() -> u32 {
+    16
+}
+
+fn default_max_live_games() -> u32 {
+    10_000
+}
+
+// About 20 minutes of 2 second sequencer blocks, far beyond the usual firm lag of a few
+// Celestia blocks
+fn default_payout_confirmations() -> u32 {
+    600
+}
+
+// A friendly game that exists before the first block, with its moves already played
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisGame {
+    pub game_id: u32,
+    pub white: String, // hex encoded public key
+    #[serde(default)]
+    pub black: Option<String>, // None leaves the game waiting for an opponent
+    #[serde(default)]
+    pub time_control: Option<TimeControl>, // Clocks of active games start with block 1
+    #[serde(default)]
+    pub moves: Vec<String>, // SAN or UCI, played alternately from white
+}
+
+impl Genesis {
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let genesis: Genesis = config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()
+            .and_then(|settings| settings.try_deserialize())
+            .wrap_err(format!("failed loading genesis from {}", path.display()))?;
+        // Surface mistakes in the file at startup rather than when the state is first built
+        genesis.game_manager()?;
+        Ok(genesis)
+    }
+
+    pub fn rollup_id(&self) -> RollupId {
+        RollupId::from_unhashed_bytes(&self.rollup_name)
+    }
+
+    pub fn genesis_info(&self) -> execution::GenesisInfo {
+        execution::GenesisInfo {
+            rollup_id: Some(self.rollup_id().into_raw()),
+            sequencer_genesis_block_height: self.sequencer_genesis_block_height,
+            celestia_block_variance: self.celestia_block_variance,
+        }
+    }
+
+    pub fn block_hash(&self) -> eyre::Result<Bytes> {
+        let mut hash = [0u8; 32];
+        hex::decode_to_slice(&self.block_hash, &mut hash)
+            .map_err(|e| eyre!("invalid genesis block hash: {}", e))?;
+        Ok(Bytes::copy_from_slice(&hash))
+    }
+
+    pub fn timestamp(&self) -> pbjson_types::Timestamp {
+        pbjson_types::Timestamp {
+            seconds: self.timestamp_seconds,
+            nanos: 0,
+        }
+    }
+
+    // App state holding only block 0
+    pub fn app_state(&self) -> eyre::Result<AppState> {
+        Ok(AppState::new(
+            self.block_hash()?,
+            self.timestamp(),
+            self.base_celestia_height,
+        ))
+    }
+
+    pub fn params(&self) -> eyre::Result<Params> {
+        let params = &self.params;
+        if params.protocol_fee_bps > 10_000 {
+            return Err(eyre!("protocol fee cannot exceed 10000 basis points"));
+        }
+        let fee_recipient = match params.protocol_fee_recipient.as_str() {
+            "" => None,
+            recipient => Some(public_key(recipient)?),
+        };
+        let deposit_holding_account = match params.deposit_holding_account.as_str() {
+            "" => None,
+            account => Some(public_key(account)?),
+        };
+        let tx_fee: u128 = params
+            .tx_fee
+            .parse()
+            .map_err(|_| eyre!("{} is not a valid transaction fee", params.tx_fee))?;
+        if tx_fee > 0 && params.fee_asset.is_empty() {
+            return Err(eyre!("a transaction fee needs a fee asset"));
+        }
+        Ok(Params {
+            fee_bps: params.protocol_fee_bps,
+            fee_recipient,
+            fee_asset: params.fee_asset.clone(),
+            tx_fee,
+            max_txs_per_block: params.max_txs_per_block,
+            max_tx_size: params.max_tx_size,
+            max_san_len: params.max_san_len,
+            max_live_games: params.max_live_games,
+            payout_confirmations: params.payout_confirmations,
+            deposit_holding_account,
+            rollup_id: self.rollup_id().get(),
+        })
+    }
+
+    // Game state before the first block: protocol parameters, seeded balances and games
+    pub fn game_manager(&self) -> eyre::Result<GameManager> {
+        let mut game_manager = GameManager::default();
+        game_manager.params = self.params()?;
+        game_manager.current_time_ms = (self.timestamp_seconds.max(0) as u64) * 1000;
+        for balance in &self.balances {
+            let account = public_key(&balance.account)?;
+            let amount: u128 = balance
+                .amount
+                .parse()
+                .map_err(|_| eyre!("genesis balance: {} is not a valid amount", balance.amount))?;
+            game_manager
+                .accounts
+                .credit(&account, &balance.asset, amount)
+                .map_err(|e| eyre!("genesis balance of {}: {}", balance.account, e))?;
+        }
+        for game in &self.games {
+            seed_game(&mut game_manager, game)
+                .map_err(|e| eyre!("genesis game {}: {}", game.game_id, e))?;
+        }
+        Ok(game_manager)
+    }
+}
+
+fn seed_game(game_manager: &mut GameManager, game: &GenesisGame) -> eyre::Result<()> {
+    game_manager
+        .start_new_game(
+            game.game_id,
+            public_key(&game.white)?,
+            game.time_control,
+            None,
+        )
+        .map_err(|e| eyre!("{}", e))?;
+    if let Some(black) = &game.black {
+        game_manager
+            .join_game(game.game_id, public_key(black)?)
+            .map_err(|e| eyre!("{}", e))?;
+    }
+    for move_str in &game.moves {
+        let player = game_manager
+            .game_status(game.game_id)
+            .map_err(|e| eyre!(e))?
+            .player_to_move()
+            .ok_or_else(|| eyre!("no player to move before {}", move_str))?;
+        game_manager
+            .make_move(game.game_id, player, move_str)
+            .map_err(|e| eyre!("{}: {}", move_str, e))?;
+    }
+    Ok(())
+}
+
+fn public_key(hex_key: &str) -> eyre::Result<PublicKey> {
+    let mut key = [0u8; 32];
+    hex::decode_to_slice(hex_key, &mut key)
+        .map_err(|e| eyre!("invalid public key {}: {}", hex_key, e))?;
+    Ok(key)
+}
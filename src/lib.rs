@@ -0,0 +1,18 @@
+pub mod accounts;
+pub mod block_log;
+pub mod config;
+pub mod dev;
+pub mod execution_service;
+pub mod game;
+pub mod genesis;
+pub mod merkle;
+pub mod notation;
+pub mod receipts;
+pub mod rest;
+pub mod rollup_app;
+mod sequencer_client;
+pub mod snapshot;
+pub mod storage;
+pub mod transaction;
+
+pub mod chess;
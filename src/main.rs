@@ -1,10 +1,4 @@
-mod config;
-mod execution_service;
-mod game;
-mod rollup_app;
-mod sequencer_client;
-
-mod chess;
+use chess_rollup::{chess, config};
 use color_eyre::eyre;
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
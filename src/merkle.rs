@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Binary Merkle tree over key/value leaves sorted by key, used for the rollup state root.
+// Leaves and inner nodes are domain separated so a leaf can never be passed off as a node,
+// and an unpaired node is carried up to the next level unchanged.
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+pub fn leaf_hash(key: &[u8], value: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update((key.len() as u32).to_be_bytes());
+    hasher.update(key);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// Root over leaf hashes in key order; the empty tree hashes to all zeroes
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks(2) yields one or two nodes"),
+        })
+        .collect()
+}
+
+// Sibling hashes from a leaf up to the root. Levels where the node was carried up unpaired
+// contribute no sibling, which the verifier recovers from the leaf index and count.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub leaf_count: usize,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+// Build the proof for the leaf at `index`, None if it is out of range
+pub fn prove(leaves: &[[u8; 32]], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut siblings = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut position = index;
+    while level.len() > 1 {
+        let sibling = position ^ 1;
+        if sibling < level.len() {
+            siblings.push(level[sibling]);
+        }
+        level = next_level(&level);
+        position /= 2;
+    }
+    Some(MerkleProof {
+        leaf_index: index,
+        leaf_count: leaves.len(),
+        siblings,
+    })
+}
+
+impl MerkleProof {
+    // Root implied by the proof for the given leaf, None if the proof is malformed
+    pub fn root(&self, leaf: [u8; 32]) -> Option<[u8; 32]> {
+        if self.leaf_index >= self.leaf_count {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = leaf;
+        let mut position = self.leaf_index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            if position % 2 == 1 {
+                hash = node_hash(siblings.next()?, &hash);
+            } else if position + 1 < width {
+                hash = node_hash(&hash, siblings.next()?);
+            }
+            position /= 2;
+            width = (width + 1) / 2;
+        }
+        match siblings.next() {
+            Some(_) => None,
+            None => Some(hash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: usize) -> Vec<[u8; 32]> {
+        (0..count)
+            .map(|i| leaf_hash(&[i as u8], b"value"))
+            .collect()
+    }
+
+    #[test]
+    fn proofs_fold_back_to_the_root() {
+        for count in [1, 2, 3, 5] {
+            let leaves = leaves(count);
+            let expected = root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = prove(&leaves, index).unwrap();
+                assert_eq!(
+                    proof.root(*leaf),
+                    Some(expected),
+                    "leaf {} of {}",
+                    index,
+                    count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn single_leaf_is_the_root() {
+        let leaves = leaves(1);
+        assert_eq!(root(&leaves), leaves[0]);
+        assert!(prove(&leaves, 0).unwrap().siblings.is_empty());
+        assert_eq!(root(&[]), [0u8; 32]);
+    }
+
+    #[test]
+    fn proofs_do_not_fit_other_leaves() {
+        let leaves = leaves(5);
+        let proof = prove(&leaves, 2).unwrap();
+        assert_ne!(proof.root(leaves[3]), Some(root(&leaves)));
+        assert!(prove(&leaves, 5).is_none());
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() {
+        let leaves = leaves(5);
+        let proof = prove(&leaves, 4).unwrap();
+        let mut extra_sibling = proof.clone();
+        extra_sibling.siblings.push([0u8; 32]);
+        assert_eq!(extra_sibling.root(leaves[4]), None);
+        let mut missing_sibling = proof.clone();
+        missing_sibling.siblings.pop();
+        assert_eq!(missing_sibling.root(leaves[4]), None);
+        let mut out_of_range = proof;
+        out_of_range.leaf_index = 5;
+        assert_eq!(out_of_range.root(leaves[4]), None);
+    }
+}
@@ -0,0 +1,280 @@
+use chess::{Board, BoardStatus, ChessMove, File, MoveGen, Piece, Rank, Square};
+use std::str::FromStr;
+
+// Parse a move given in SAN (`Nf3`, `exd5`, `O-O`) or UCI (`g1f3`, `e7e8q`) and check it is legal
+pub fn parse_move(board: &Board, move_str: &str) -> Result<ChessMove, String> {
+    let move_str = move_str.trim().trim_end_matches(['+', '#']);
+    let chess_move = ChessMove::from_san(board, move_str)
+        .ok()
+        .or_else(|| from_uci(move_str))
+        .ok_or_else(|| format!("Invalid move: {}", move_str))?;
+
+    if !board.legal(chess_move) {
+        return Err(format!("Illegal move: {}", move_str));
+    }
+    Ok(chess_move)
+}
+
+// Format a legal move in Standard Algebraic Notation for the given position
+pub fn to_san(board: &Board, chess_move: ChessMove) -> String {
+    let source = chess_move.get_source();
+    let dest = chess_move.get_dest();
+    let piece = board.piece_on(source).unwrap_or(Piece::Pawn);
+    let mut san = String::new();
+
+    let file_distance =
+        (source.get_file().to_index() as i32 - dest.get_file().to_index() as i32).abs();
+    if piece == Piece::King && file_distance == 2 {
+        san.push_str(if dest.get_file() == File::G {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else {
+        // Pawns changing file always capture, even en passant onto an empty square
+        let capture = board.piece_on(dest).is_some()
+            || (piece == Piece::Pawn && source.get_file() != dest.get_file());
+
+        if piece == Piece::Pawn {
+            if capture {
+                san.push(file_char(source.get_file()));
+            }
+        } else {
+            san.push(piece_char(piece));
+            san.push_str(&disambiguation(board, piece, source, dest));
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&dest.to_string());
+
+        if let Some(promotion) = chess_move.get_promotion() {
+            san.push('=');
+            san.push(piece_char(promotion));
+        }
+    }
+
+    let next = board.make_move_new(chess_move);
+    if next.status() == BoardStatus::Checkmate {
+        san.push('#');
+    } else if next.checkers().popcnt() > 0 {
+        san.push('+');
+    }
+
+    san
+}
+
+// Format a move in UCI long algebraic notation (e.g. `e2e4`, `e7e8q`)
+pub fn to_uci(chess_move: ChessMove) -> String {
+    chess_move.to_string()
+}
+
+// Parse a UCI long algebraic move without checking legality
+fn from_uci(move_str: &str) -> Option<ChessMove> {
+    if !move_str.is_ascii() || !(move_str.len() == 4 || move_str.len() == 5) {
+        return None;
+    }
+    let source = Square::from_str(&move_str[0..2]).ok()?;
+    let dest = Square::from_str(&move_str[2..4]).ok()?;
+    let promotion = match move_str.get(4..5) {
+        None => None,
+        Some("q") => Some(Piece::Queen),
+        Some("r") => Some(Piece::Rook),
+        Some("b") => Some(Piece::Bishop),
+        Some("n") => Some(Piece::Knight),
+        Some(_) => return None,
+    };
+    Some(ChessMove::new(source, dest, promotion))
+}
+
+// Replay a move list from the starting position and format every move in SAN
+pub fn san_moves(moves: &[ChessMove]) -> Vec<String> {
+    let mut board = Board::default();
+    let mut san = Vec::with_capacity(moves.len());
+    for chess_move in moves {
+        san.push(to_san(&board, *chess_move));
+        board = board.make_move_new(*chess_move);
+    }
+    san
+}
+
+// Extra file and/or rank needed when several pieces of the same type can reach `dest`
+fn disambiguation(board: &Board, piece: Piece, source: Square, dest: Square) -> String {
+    let others: Vec<Square> = MoveGen::new_legal(board)
+        .filter(|mv| mv.get_dest() == dest && mv.get_source() != source)
+        .filter(|mv| board.piece_on(mv.get_source()) == Some(piece))
+        .map(|mv| mv.get_source())
+        .collect();
+
+    if others.is_empty() {
+        return String::new();
+    }
+
+    let shares_file = others.iter().any(|sq| sq.get_file() == source.get_file());
+    let shares_rank = others.iter().any(|sq| sq.get_rank() == source.get_rank());
+    match (shares_file, shares_rank) {
+        (false, _) => file_char(source.get_file()).to_string(),
+        (true, false) => rank_char(source.get_rank()).to_string(),
+        (true, true) => source.to_string(),
+    }
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+fn file_char(file: File) -> char {
+    (b'a' + file.to_index() as u8) as char
+}
+
+fn rank_char(rank: Rank) -> char {
+    (b'1' + rank.to_index() as u8) as char
+}
+
+// Serde helpers storing chess types in their textual notations
+
+// `Game` stored as the FEN of its current position
+pub mod serde_fen {
+    use chess::Game;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(game: &Game, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&game.current_position().to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        Game::from_str(&fen).map_err(|_| D::Error::custom(format!("invalid FEN: {}", fen)))
+    }
+}
+
+// Move lists stored as UCI strings
+pub mod serde_uci_moves {
+    use chess::ChessMove;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(moves: &[ChessMove], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(moves.iter().map(|mv| super::to_uci(*mv)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<ChessMove>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|mv| {
+                super::from_uci(mv).ok_or_else(|| D::Error::custom(format!("invalid move: {}", mv)))
+            })
+            .collect()
+    }
+}
+
+// Optional colors stored as `white` / `black`
+pub mod serde_opt_color {
+    use chess::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        color: &Option<Color>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match color {
+            Some(Color::White) => serializer.serialize_some("white"),
+            Some(Color::Black) => serializer.serialize_some("black"),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Color>, D::Error> {
+        match Option::<String>::deserialize(deserializer)?.as_deref() {
+            Some("white") => Ok(Some(Color::White)),
+            Some("black") => Ok(Some(Color::Black)),
+            Some(other) => Err(D::Error::custom(format!("invalid color: {}", other))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    fn san(fen: &str, uci: &str) -> String {
+        to_san(&board(fen), from_uci(uci).unwrap())
+    }
+
+    #[test]
+    fn castling() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, "e1g1"), "O-O");
+        assert_eq!(san(fen, "e1c1"), "O-O-O");
+        let castled = parse_move(&board(fen), "O-O").unwrap();
+        assert_eq!(to_uci(castled), "e1g1");
+    }
+
+    #[test]
+    fn promotion() {
+        let fen = "8/P7/8/8/8/2k5/8/7K w - - 0 1";
+        assert_eq!(san(fen, "a7a8q"), "a8=Q");
+        assert_eq!(san(fen, "a7a8n"), "a8=N");
+        let promoted = parse_move(&board(fen), "a7a8n").unwrap();
+        assert_eq!(promoted.get_promotion(), Some(Piece::Knight));
+    }
+
+    #[test]
+    fn en_passant_is_a_capture() {
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn check_and_mate_suffixes() {
+        assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8+");
+        let fools_mate: Vec<ChessMove> = ["f2f3", "e7e5", "g2g4", "d8h4"]
+            .iter()
+            .map(|mv| from_uci(mv).unwrap())
+            .collect();
+        assert_eq!(san_moves(&fools_mate), vec!["f3", "e5", "g4", "Qh4#"]);
+        // Suffixes are accepted, and ignored, when parsing
+        let board = board("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(
+            parse_move(&board, "Ra8+").unwrap(),
+            from_uci("a1a8").unwrap()
+        );
+    }
+
+    #[test]
+    fn disambiguation_by_file_rank_or_square() {
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("4k3/8/8/1N6/8/8/8/1N2K3 w - - 0 1", "b1c3"), "N1c3");
+        assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q4K w - - 0 1", "a1b2"), "Qa1b2");
+        // No disambiguation when only one piece of the kind can reach the square
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", "b1d2"), "Nd2");
+    }
+
+    #[test]
+    fn illegal_and_malformed_moves_are_rejected() {
+        let start = Board::default();
+        assert!(parse_move(&start, "e2e5").is_err());
+        assert!(parse_move(&start, "Ke2").is_err());
+        assert!(parse_move(&start, "zz").is_err());
+        assert_eq!(
+            parse_move(&start, " e4 ").unwrap(),
+            from_uci("e2e4").unwrap()
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::game::{EndReason, Outcome};
+use crate::transaction::Transaction;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Outcome of a single transaction or deposit, looked up by clients through its hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Receipt {
+    pub tx_hash: [u8; 32],
+    pub height: u32,
+    pub index: u32,             // Position among the block's rollup data
+    pub game_id: Option<u32>,   // Game created or touched by the transaction
+    pub error: Option<TxError>, // None if the transaction was applied
+    pub events: Vec<Event>,     // Fees are reported even for failed transactions
+}
+
+// Machine readable reason a transaction was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    DecodeFailed,
+    LimitExceeded,
+    InvalidSignature,
+    InvalidNonce,
+    InsufficientFunds,
+    GameNotFound,
+    GameExists,
+    InvalidGameState, // Waiting for an opponent, or already over
+    NotAPlayer,
+    NotYourTurn,
+    IllegalMove,
+    InvalidDeposit,
+    Rejected, // Any other rule violation
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl TxError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// Untyped rule violations
+impl From<String> for TxError {
+    fn from(message: String) -> Self {
+        TxError::new(ErrorCode::Rejected, message)
+    }
+}
+
+impl From<&str> for TxError {
+    fn from(message: &str) -> Self {
+        TxError::new(ErrorCode::Rejected, message)
+    }
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// State changes reported in receipts, and in the block body for those that happen at the
+// start of a block. Keys are hex encoded and amounts are decimal strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    GameStarted {
+        game_id: u32,
+        white: String,
+    },
+    GameJoined {
+        game_id: u32,
+        black: String,
+    },
+    MoveMade {
+        game_id: u32,
+        uci: String,
+    },
+    DrawOffered {
+        game_id: u32,
+    },
+    DrawDeclined {
+        game_id: u32,
+    },
+    GameEnded {
+        game_id: u32,
+        result: Outcome,
+        reason: EndReason,
+    },
+    GameAborted {
+        game_id: u32,
+    },
+    Deposited {
+        account: String,
+        asset: String,
+        amount: String,
+    },
+    DepositHeld {
+        destination: String, // As given, not a rollup account
+        account: String,     // Holding account credited instead
+        asset: String,
+        amount: String,
+    },
+    WithdrawalRequested {
+        withdrawal_id: u64,
+        asset: String,
+        amount: String,
+        destination: String,
+    },
+    FeeCharged {
+        account: String,
+        asset: String,
+        amount: String,
+    },
+    PayoutReleased {
+        game_id: u32,
+        account: String,
+        asset: String,
+        amount: String,
+    },
+}
+
+// Everything a block's execution produced, stored per block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockBody {
+    pub transactions: Vec<BlockTransaction>, // Rollup data in sequencer order, one per receipt
+    pub events: Vec<Event>, // Timeouts and payouts applied before the first transaction
+    pub receipts: Vec<Receipt>,
+}
+
+// Rollup data of a block as sequenced, next to its decoded form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTransaction {
+    pub index: u32,
+    pub size: u32,          // Length of the data in bytes; its hash is in the receipt
+    pub raw: Option<Bytes>, // Encoded signed transaction or deposit, None if over the limits
+    pub decoded: Option<DecodedInput>, // None if the data was rejected before or while decoding
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodedInput {
+    Transaction {
+        signer: String, // hex encoded public key
+        nonce: u64,
+        transaction: Transaction,
+    },
+    Deposit {
+        destination: String,
+        asset: String,
+        amount: String,
+        source_transaction_id: String,
+        source_action_index: u64,
+    },
+}
@@ -0,0 +1,1358 @@
+use crate::accounts::Withdrawal;
+use crate::game::{
+    self, EndReason, GameManager, GameState, GameStatus, Outcome, Stake, TimeControl,
+};
+use crate::notation;
+use crate::receipts::{BlockBody, BlockTransaction, DecodedInput, ErrorCode, Event, Receipt};
+use crate::rollup_app::AppState;
+use crate::snapshot::Snapshot;
+use crate::transaction::{self, DrawClaim, SignedTransaction, Transaction};
+use astria_core::generated::composer::v1::grpc_collector_service_client::GrpcCollectorServiceClient;
+use astria_core::generated::composer::v1::SubmitRollupTransactionRequest;
+use astria_core::generated::primitive::v1::RollupId;
+use chess::Color;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use warp::http::StatusCode;
+use warp::Filter;
+
+// Composer client that submits rollup data under this rollup's id
+#[derive(Clone)]
+pub struct Composer {
+    pub client: GrpcCollectorServiceClient<tonic::transport::channel::Channel>,
+    pub rollup_id: RollupId,
+}
+
+// Errors returned by the REST API, rendered as `ErrorResponse` bodies
+#[derive(Debug)]
+pub enum ApiError {
+    GameNotFound(u32),
+    ReceiptNotFound(String),
+    BlockNotFound(u32),
+    ProofUnavailable(String),
+    SnapshotUnavailable(String),
+    GameExists(u32),
+    GameFinished(u32),
+    IllegalMove { game_id: u32, reason: String },
+    NotYourTurn(u32),
+    InvalidRequest(String),
+    InvalidSignature(String),
+    Composer(String),
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::GameNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::ReceiptNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BlockNotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::ProofUnavailable(_) => StatusCode::CONFLICT,
+            ApiError::SnapshotUnavailable(_) => StatusCode::CONFLICT,
+            ApiError::GameExists(_) => StatusCode::CONFLICT,
+            ApiError::GameFinished(_) => StatusCode::CONFLICT,
+            ApiError::IllegalMove { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::NotYourTurn(_) => StatusCode::FORBIDDEN,
+            ApiError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::InvalidSignature(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Composer(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::GameNotFound(_) => "game_not_found",
+            ApiError::ReceiptNotFound(_) => "receipt_not_found",
+            ApiError::BlockNotFound(_) => "block_not_found",
+            ApiError::ProofUnavailable(_) => "proof_unavailable",
+            ApiError::SnapshotUnavailable(_) => "snapshot_unavailable",
+            ApiError::GameExists(_) => "game_exists",
+            ApiError::GameFinished(_) => "game_finished",
+            ApiError::IllegalMove { .. } => "illegal_move",
+            ApiError::NotYourTurn(_) => "not_your_turn",
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::InvalidSignature(_) => "invalid_signature",
+            ApiError::Composer(_) => "composer_error",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::GameNotFound(game_id) => write!(f, "game {} not found", game_id),
+            ApiError::ReceiptNotFound(tx_hash) => {
+                write!(f, "no receipt for transaction {}", tx_hash)
+            }
+            ApiError::BlockNotFound(height) => write!(f, "no executed block at height {}", height),
+            ApiError::ProofUnavailable(message) => write!(f, "proof unavailable: {}", message),
+            ApiError::SnapshotUnavailable(message) => {
+                write!(f, "snapshot unavailable: {}", message)
+            }
+            ApiError::GameExists(game_id) => write!(f, "game {} already exists", game_id),
+            ApiError::GameFinished(game_id) => write!(f, "game {} is already finished", game_id),
+            ApiError::IllegalMove { game_id, reason } => {
+                write!(f, "illegal move in game {}: {}", game_id, reason)
+            }
+            ApiError::NotYourTurn(game_id) => {
+                write!(f, "signer is not the player to move in game {}", game_id)
+            }
+            ApiError::InvalidRequest(message) => write!(f, "invalid request: {}", message),
+            ApiError::InvalidSignature(message) => write!(f, "invalid signature: {}", message),
+            ApiError::Composer(message) => write!(f, "composer rejected transaction: {}", message),
+        }
+    }
+}
+
+impl warp::reject::Reject for ApiError {}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub code: String,
+    pub message: String,
+}
+
+// Signature fields shared by every submission body.
+// Clients sign `SignedTransaction::signing_bytes(rollup_id, nonce, transaction)` with their
+// ed25519 key.
+#[derive(Debug, Deserialize)]
+pub struct SignedRequest {
+    pub public_key: String, // hex encoded verification key
+    pub signature: String,  // hex encoded signature
+    pub nonce: u64,         // Covered by the signature, see `GET /accounts/{public_key}/nonce`
+}
+
+// Body of `POST /create_game/{game_id}` and `POST /games`
+#[derive(Debug, Deserialize)]
+pub struct CreateGameRequest {
+    pub time_control: Option<TimeControlRequest>,
+    pub stake: Option<StakeRequest>,
+    #[serde(flatten)]
+    pub signer: SignedRequest,
+}
+
+// e.g. `{"clock": {"initial_ms": 300000, "increment_ms": 2000}}` or `{"blocks": {"per_move": 100}}`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeControlRequest {
+    Clock { initial_ms: u64, increment_ms: u64 },
+    Blocks { per_move: u32 },
+}
+
+impl From<TimeControlRequest> for TimeControl {
+    fn from(request: TimeControlRequest) -> Self {
+        match request {
+            TimeControlRequest::Clock {
+                initial_ms,
+                increment_ms,
+            } => TimeControl::Clock {
+                initial_ms,
+                increment_ms,
+            },
+            TimeControlRequest::Blocks { per_move } => TimeControl::Blocks { per_move },
+        }
+    }
+}
+
+// Wager escrowed from each player; the amount is a decimal string
+#[derive(Debug, Deserialize)]
+pub struct StakeRequest {
+    pub asset: String,
+    pub amount: String,
+}
+
+impl TryFrom<StakeRequest> for Stake {
+    type Error = ApiError;
+
+    fn try_from(request: StakeRequest) -> Result<Self, Self::Error> {
+        let amount: u128 = request.amount.parse().map_err(|_| {
+            ApiError::InvalidRequest(format!("stake: {} is not a valid amount", request.amount))
+        })?;
+        if amount == 0 {
+            return Err(ApiError::InvalidRequest(
+                "stake: amount must be positive".into(),
+            ));
+        }
+        Ok(Stake {
+            asset: request.asset,
+            amount,
+        })
+    }
+}
+
+// Body of `POST /games/{game_id}/moves`
+#[derive(Debug, Deserialize)]
+pub struct MoveRequest {
+    #[serde(rename = "move")]
+    pub mv: String, // SAN or UCI
+    #[serde(flatten)]
+    pub signer: SignedRequest,
+}
+
+// Body of `POST /games/{game_id}/{action}`
+#[derive(Debug, Deserialize)]
+pub struct ActionRequest {
+    pub claim: Option<String>, // `repetition` or `fifty_moves`, only used by `claim_draw`
+    #[serde(flatten)]
+    pub signer: SignedRequest,
+}
+
+impl SignedRequest {
+    // Wrap a transaction in a signed envelope and check the signature
+    async fn into_signed(
+        self,
+        transaction: Transaction,
+        game_manager: &Arc<RwLock<GameManager>>,
+    ) -> Result<SignedTransaction, ApiError> {
+        let mut public_key = [0u8; 32];
+        hex::decode_to_slice(&self.public_key, &mut public_key)
+            .map_err(|e| ApiError::InvalidRequest(format!("public_key: {}", e)))?;
+        let mut signature = [0u8; 64];
+        hex::decode_to_slice(&self.signature, &mut signature)
+            .map_err(|e| ApiError::InvalidRequest(format!("signature: {}", e)))?;
+
+        let signed_tx = SignedTransaction {
+            public_key,
+            signature,
+            nonce: self.nonce,
+            transaction,
+        };
+        let rollup_id = game_manager.read().await.params.rollup_id;
+        signed_tx
+            .verify(&rollup_id)
+            .map_err(ApiError::InvalidSignature)?;
+        Ok(signed_tx)
+    }
+}
+
+// Returned once a transaction has been handed to the composer
+#[derive(Debug, Serialize)]
+pub struct SubmitResponse {
+    pub tx_hash: String, // Hash to look the receipt up with once the tx is executed
+    pub game_id: Option<u32>, // None when the rollup assigns the id
+    pub status: &'static str,
+}
+
+// Outcome of an executed transaction
+#[derive(Debug, Serialize)]
+pub struct ReceiptView {
+    pub tx_hash: String,
+    pub height: u32,
+    pub index: u32,
+    pub game_id: Option<u32>,
+    pub success: bool,
+    pub error_code: Option<ErrorCode>,
+    pub error: Option<String>,
+    pub events: Vec<Event>,
+}
+
+impl ReceiptView {
+    fn new(receipt: &Receipt) -> Self {
+        Self {
+            tx_hash: hex::encode(receipt.tx_hash),
+            height: receipt.height,
+            index: receipt.index,
+            game_id: receipt.game_id,
+            success: receipt.error.is_none(),
+            error_code: receipt.error.as_ref().map(|e| e.code),
+            error: receipt.error.as_ref().map(|e| e.message.clone()),
+            events: receipt.events.clone(),
+        }
+    }
+}
+
+// Execution results of a block: events from the start of the block, then every receipt
+#[derive(Debug, Serialize)]
+pub struct BlockResultsView {
+    pub height: u32,
+    pub events: Vec<Event>,
+    pub receipts: Vec<ReceiptView>,
+}
+
+impl BlockResultsView {
+    fn new(height: u32, body: &BlockBody) -> Self {
+        Self {
+            height,
+            events: body.events.clone(),
+            receipts: body.receipts.iter().map(ReceiptView::new).collect(),
+        }
+    }
+}
+
+// Commitments of an executed block; the hash is
+// sha256(parent_hash || height || timestamp_ms || transactions_root || state_root), with
+// big-endian integers
+#[derive(Debug, Serialize)]
+pub struct StateRootView {
+    pub height: u32,
+    pub timestamp_ms: u64, // Block timestamp as seen by execution
+    pub hash: String,
+    pub parent_hash: String,
+    pub transactions_root: String,
+    pub state_root: String,
+}
+
+// Most blocks served by one `GET /blocks` request
+const MAX_BLOCK_RANGE: u32 = 100;
+
+// Query of `GET /blocks`, an inclusive range of heights
+#[derive(Debug, Deserialize)]
+pub struct BlocksQuery {
+    pub from: u32,
+    pub to: u32,
+}
+
+// Rollup data as sequenced in a block, with its decoded form and receipt
+#[derive(Debug, Serialize)]
+pub struct TransactionView {
+    pub tx_hash: String,
+    pub height: u32,
+    pub index: u32,
+    pub size: u32,
+    pub raw: Option<String>, // hex encoded, None for data over the block's limits
+    pub decoded: Option<DecodedInput>, // None if the data could not be decoded
+    pub receipt: ReceiptView,
+}
+
+impl TransactionView {
+    fn new(transaction: &BlockTransaction, receipt: &Receipt) -> Self {
+        Self {
+            tx_hash: hex::encode(receipt.tx_hash),
+            height: receipt.height,
+            index: transaction.index,
+            size: transaction.size,
+            raw: transaction.raw.as_ref().map(hex::encode),
+            decoded: transaction.decoded.clone(),
+            receipt: ReceiptView::new(receipt),
+        }
+    }
+}
+
+// Header, commitments and full body of an executed block
+#[derive(Debug, Serialize)]
+pub struct BlockView {
+    pub height: u32,
+    pub timestamp_ms: u64, // Block timestamp as seen by execution, part of the hash preimage
+    pub hash: String,
+    pub parent_hash: String,
+    pub transactions_root: String,
+    pub state_root: String,
+    pub events: Vec<Event>,
+    pub transactions: Vec<TransactionView>,
+}
+
+impl BlockView {
+    // None unless the block was executed by this node, which rules out genesis
+    fn new(app: &AppState, height: u32) -> Option<Self> {
+        let block = app.get_block(height)?;
+        let roots = app.get_block_roots(height)?;
+        let body = app.get_block_body(height)?;
+        Some(Self {
+            height,
+            timestamp_ms: game::timestamp_ms(&block.timestamp()),
+            hash: hex::encode(block.hash()),
+            parent_hash: hex::encode(block.parent_block_hash()),
+            transactions_root: hex::encode(roots.transactions_root),
+            state_root: hex::encode(roots.state_root),
+            events: body.events.clone(),
+            transactions: body
+                .transactions
+                .iter()
+                .zip(&body.receipts)
+                .map(|(transaction, receipt)| TransactionView::new(transaction, receipt))
+                .collect(),
+        })
+    }
+}
+
+// Query of `GET /games/{game_id}/proof`; proofs are against the soft block by default, and
+// any block from firm to soft can be named
+#[derive(Debug, Deserialize)]
+pub struct ProofQuery {
+    pub height: Option<u32>,
+}
+
+// Inclusion proof of a game's state against the block at `height`.
+// Verify by folding `leaf_hash(key, value)` with `siblings` into `state_root`, then checking
+// sha256(parent_hash || height || timestamp_ms || transactions_root || state_root), with
+// big-endian integers, against `block_hash`. The key must be `game_key(game_id)` and the value
+// decodes to the game state; `StateProof::verify_game` performs all of these checks, and
+// bridges must rely on it rather than on the `game` summary.
+#[derive(Debug, Serialize)]
+pub struct GameProofView {
+    pub game_id: u32,
+    pub height: u32,
+    pub timestamp_ms: u64,
+    pub block_hash: String,
+    pub parent_hash: String,
+    pub transactions_root: String,
+    pub state_root: String,
+    pub key: String,   // hex encoded state key
+    pub value: String, // hex encoded JSON game state
+    pub leaf_index: usize,
+    pub leaf_count: usize,
+    pub siblings: Vec<String>,
+    pub game: GameSummary,
+}
+
+// Body of `POST /withdrawals`; the amount is a decimal string
+#[derive(Debug, Deserialize)]
+pub struct WithdrawRequest {
+    pub asset: String,
+    pub amount: String,
+    pub destination: String, // Sequencer address receiving the funds
+    #[serde(default)]
+    pub memo: String,
+    #[serde(flatten)]
+    pub signer: SignedRequest,
+}
+
+// Query of `GET /withdrawals`, all bounds optional
+#[derive(Debug, Deserialize)]
+pub struct WithdrawalsQuery {
+    pub from_height: Option<u32>,
+    pub to_height: Option<u32>,
+    pub status: Option<String>, // "pending" or "completed"
+}
+
+// Withdrawal event; completed once the block that executed it is firm
+#[derive(Debug, Serialize)]
+pub struct WithdrawalView {
+    pub id: u64,
+    pub account: String,
+    pub asset: String,
+    pub amount: String,
+    pub destination: String,
+    pub memo: String,
+    pub height: u32,
+    pub status: &'static str,
+}
+
+impl WithdrawalView {
+    fn new(withdrawal: &Withdrawal, firm_height: u32) -> Self {
+        Self {
+            id: withdrawal.id,
+            account: hex::encode(withdrawal.account),
+            asset: withdrawal.asset.clone(),
+            amount: withdrawal.amount.to_string(),
+            destination: withdrawal.destination.clone(),
+            memo: withdrawal.memo.clone(),
+            height: withdrawal.height,
+            status: withdrawal_status(withdrawal, firm_height),
+        }
+    }
+}
+
+// Next nonce an account must sign with
+#[derive(Debug, Serialize)]
+pub struct NonceView {
+    pub account: String,
+    pub nonce: u64,
+    pub as_of_height: u32,
+}
+
+// Balances of a rollup account. Amounts are decimal strings since they may exceed 2^53.
+#[derive(Debug, Serialize)]
+pub struct BalancesView {
+    pub account: String,
+    pub balances: Vec<BalanceView>,
+    pub as_of_height: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceView {
+    pub asset: String,
+    pub amount: String,
+}
+
+// Escrow of a wagered game
+#[derive(Debug, Serialize)]
+pub struct StakeView {
+    pub asset: String,
+    pub amount: String, // Per player
+    pub pot: String,
+    pub released: bool, // Paid out once the result block has enough confirmations
+}
+
+impl StakeView {
+    fn new(game_state: &GameState) -> Option<Self> {
+        let escrow = game_state.escrow.as_ref()?;
+        Some(Self {
+            asset: escrow.stake.asset.clone(),
+            amount: escrow.stake.amount.to_string(),
+            pot: escrow.pot.to_string(),
+            released: escrow.released,
+        })
+    }
+}
+
+// Remaining time for both sides as of the latest executed block
+#[derive(Debug, Serialize)]
+pub struct ClockView {
+    pub white_remaining_ms: Option<u64>, // Only set for clock based games
+    pub black_remaining_ms: Option<u64>,
+    pub move_deadline_height: Option<u32>, // Only set for block based games
+    pub as_of_height: u32,
+}
+
+impl ClockView {
+    fn new(game_state: &GameState, game_manager: &GameManager) -> Option<Self> {
+        game_state.time_control?;
+        let now_ms = game_manager.current_time_ms;
+        Some(Self {
+            white_remaining_ms: game_state.remaining_ms(Color::White, now_ms),
+            black_remaining_ms: game_state.remaining_ms(Color::Black, now_ms),
+            move_deadline_height: game_state.move_deadline(),
+            as_of_height: game_manager.current_height,
+        })
+    }
+}
+
+// Full view of a single game
+#[derive(Debug, Serialize)]
+pub struct GameView {
+    pub game_id: u32,
+    pub white: String,
+    pub black: Option<String>,
+    pub fen: String,
+    pub side_to_move: &'static str,
+    pub moves_san: Vec<String>,
+    pub moves_uci: Vec<String>,
+    pub move_count: usize,
+    pub status: &'static str,
+    pub result: Option<&'static str>,
+    pub reason: Option<&'static str>,
+    pub winner: Option<&'static str>,
+    pub draw_offer: Option<&'static str>,
+    pub clock: Option<ClockView>,
+    pub stake: Option<StakeView>,
+    pub last_updated_height: u32,
+}
+
+// Condensed view of a game used when listing games
+#[derive(Debug, Serialize)]
+pub struct GameSummary {
+    pub game_id: u32,
+    pub white: String,
+    pub black: Option<String>,
+    pub side_to_move: &'static str,
+    pub move_count: usize,
+    pub status: &'static str,
+    pub result: Option<&'static str>,
+    pub reason: Option<&'static str>,
+    pub winner: Option<&'static str>,
+    pub draw_offer: Option<&'static str>,
+    pub clock: Option<ClockView>,
+    pub stake: Option<StakeView>,
+    pub last_updated_height: u32,
+}
+
+impl GameView {
+    fn new(game_id: u32, game_state: &GameState, game_manager: &GameManager) -> Self {
+        let position = game_state.game.current_position();
+        Self {
+            game_id,
+            white: hex::encode(game_state.white),
+            black: game_state.black.map(hex::encode),
+            fen: position.to_string(),
+            side_to_move: color_name(position.side_to_move()),
+            moves_san: notation::san_moves(&game_state.moves),
+            moves_uci: game_state
+                .moves
+                .iter()
+                .map(|mv| notation::to_uci(*mv))
+                .collect(),
+            move_count: game_state.moves.len(),
+            status: status_name(game_state.status),
+            result: outcome(game_state.status).map(|(result, _)| outcome_name(result)),
+            reason: outcome(game_state.status).map(|(_, reason)| reason_name(reason)),
+            winner: game_state.winner().map(color_name),
+            draw_offer: game_state.draw_offer.map(color_name),
+            clock: ClockView::new(game_state, game_manager),
+            stake: StakeView::new(game_state),
+            last_updated_height: game_state.last_updated,
+        }
+    }
+}
+
+impl GameSummary {
+    fn new(game_id: u32, game_state: &GameState, game_manager: &GameManager) -> Self {
+        Self {
+            game_id,
+            white: hex::encode(game_state.white),
+            black: game_state.black.map(hex::encode),
+            side_to_move: color_name(game_state.game.side_to_move()),
+            move_count: game_state.moves.len(),
+            status: status_name(game_state.status),
+            result: outcome(game_state.status).map(|(result, _)| outcome_name(result)),
+            reason: outcome(game_state.status).map(|(_, reason)| reason_name(reason)),
+            winner: game_state.winner().map(color_name),
+            draw_offer: game_state.draw_offer.map(color_name),
+            clock: ClockView::new(game_state, game_manager),
+            stake: StakeView::new(game_state),
+            last_updated_height: game_state.last_updated,
+        }
+    }
+}
+
+pub fn routes(
+    app: Arc<RwLock<AppState>>,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Infallible> + Clone {
+    // `POST /create_game/{game_id}` endpoint to create a new game
+    let create_game = warp::path!("create_game" / u32)
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_game_manager(game_manager.clone()))
+        .and(with_composer(composer_client.clone()))
+        .and_then(handle_create_game);
+    // `POST /games` endpoint to create a new game with a rollup assigned id
+    let create_assigned_game = warp::path!("games")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_game_manager(game_manager.clone()))
+        .and(with_composer(composer_client.clone()))
+        .and_then(handle_create_assigned_game);
+    // `POST /games/{game_id}/join` endpoint to join an open game as black
+    let join_game = warp::path!("games" / u32 / "join")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_game_manager(game_manager.clone()))
+        .and(with_composer(composer_client.clone()))
+        .and_then(handle_join_game);
+    // `POST /games/{game_id}/moves` endpoint to submit a move in SAN or UCI
+    let make_move = warp::path!("games" / u32 / "moves")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_game_manager(game_manager.clone()))
+        .and(with_composer(composer_client.clone()))
+        .and_then(handle_make_move);
+    // `POST /games/{game_id}/{action}` endpoint to resign, offer, accept, decline or claim a
+    // draw, or abort the game
+    let game_action = warp::path!("games" / u32 / String)
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_game_manager(game_manager.clone()))
+        .and(with_composer(composer_client))
+        .and_then(handle_game_action);
+    // `GET /games` endpoint to list all games
+    let list_games = warp::path!("games")
+        .and(warp::get())
+        .and(with_game_manager(game_manager.clone()))
+        .and_then(handle_list_games);
+    // `GET /games/{game_id}` endpoint to get a single game
+    let get_game = warp::path!("games" / u32)
+        .and(warp::get())
+        .and(with_game_manager(game_manager.clone()))
+        .and_then(handle_get_game);
+    // `GET /game_status/{game_id}` endpoint to get game status
+    let game_status = warp::path!("game_status" / u32)
+        .and(warp::get())
+        .and(with_game_manager(game_manager))
+        .and_then(handle_get_game);
+    // `GET /games/{game_id}/proof?height=` endpoint to prove a game's state against a block
+    let get_game_proof = warp::path!("games" / u32 / "proof")
+        .and(warp::get())
+        .and(warp::query::<ProofQuery>())
+        .and(with_app(app.clone()))
+        .and_then(handle_get_game_proof);
+    // `GET /accounts/{public_key}/balances` endpoint to query deposited funds
+    let get_balances = warp::path!("accounts" / String / "balances")
+        .and(warp::get())
+        .and(with_game_manager(game_manager.clone()))
+        .and_then(handle_get_balances);
+    // `POST /withdrawals` endpoint to move funds back to the sequencer
+    let withdraw = warp::path!("withdrawals")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_game_manager(game_manager.clone()))
+        .and(with_composer(composer_client.clone()))
+        .and_then(handle_withdraw);
+    // `GET /withdrawals` endpoint for bridge withdrawers to pick up withdrawal events
+    let list_withdrawals = warp::path!("withdrawals")
+        .and(warp::get())
+        .and(warp::query::<WithdrawalsQuery>())
+        .and(with_app(app.clone()))
+        .and(with_game_manager(game_manager.clone()))
+        .and_then(handle_list_withdrawals);
+    // `GET /accounts/{public_key}/nonce` endpoint to fetch the nonce for the next transaction
+    let get_nonce = warp::path!("accounts" / String / "nonce")
+        .and(warp::get())
+        .and(with_game_manager(game_manager.clone()))
+        .and_then(handle_get_nonce);
+    // `GET /receipts/{tx_hash}` endpoint to look up the outcome of a transaction
+    let get_receipt = warp::path!("receipts" / String)
+        .and(warp::get())
+        .and(with_app(app.clone()))
+        .and_then(handle_get_receipt);
+    // `GET /transactions/{tx_hash}` endpoint to fetch a transaction with its receipt
+    let get_transaction = warp::path!("transactions" / String)
+        .and(warp::get())
+        .and(with_app(app.clone()))
+        .and_then(handle_get_transaction);
+    // `GET /blocks/{height}` endpoint to fetch a block with its transactions and receipts
+    let get_block = warp::path!("blocks" / u32)
+        .and(warp::get())
+        .and(with_app(app.clone()))
+        .and_then(handle_get_block);
+    // `GET /blocks?from={height}&to={height}` endpoint to fetch a range of blocks
+    let list_blocks = warp::path!("blocks")
+        .and(warp::get())
+        .and(warp::query::<BlocksQuery>())
+        .and(with_app(app.clone()))
+        .and_then(handle_list_blocks);
+    // `GET /blocks/{height}/receipts` endpoint to fetch the execution results of a block
+    let get_block_results = warp::path!("blocks" / u32 / "receipts")
+        .and(warp::get())
+        .and(with_app(app.clone()))
+        .and_then(handle_get_block_results);
+    // `GET /snapshot` endpoint to export the state at the firm height, saved to a file with
+    // e.g. `curl -o snapshot.json localhost:3030/snapshot`; the `snapshot` binary writes one
+    // from the database of a stopped node
+    let get_snapshot = warp::path!("snapshot")
+        .and(warp::get())
+        .and(with_app(app.clone()))
+        .and_then(handle_get_snapshot);
+    // `GET /blocks/{height}/state_root` endpoint to fetch the roots committed by a block
+    let get_state_root = warp::path!("blocks" / u32 / "state_root")
+        .and(warp::get())
+        .and(with_app(app))
+        .and_then(handle_get_state_root);
+
+    create_game
+        .or(create_assigned_game)
+        .or(join_game)
+        .or(make_move)
+        .or(game_action)
+        .or(list_games)
+        .or(get_game)
+        .or(get_game_proof)
+        .or(game_status)
+        .or(get_balances)
+        .or(get_nonce)
+        .or(withdraw)
+        .or(list_withdrawals)
+        .or(get_receipt)
+        .or(get_transaction)
+        .or(get_block)
+        .or(list_blocks)
+        .or(get_block_results)
+        .or(get_state_root)
+        .or(get_snapshot)
+        .recover(handle_rejection)
+}
+
+// Helper function to pass the composer client as a filter to endpoints
+fn with_composer(
+    composer_client: Composer,
+) -> impl Filter<Extract = (Composer,), Error = Infallible> + Clone {
+    warp::any().map(move || composer_client.clone())
+}
+
+// Helper function to pass `AppState` as a filter to endpoints
+fn with_app(
+    app: Arc<RwLock<AppState>>,
+) -> impl Filter<Extract = (Arc<RwLock<AppState>>,), Error = Infallible> + Clone {
+    warp::any().map(move || app.clone())
+}
+
+// Helper function to pass `GameManager` as a filter to endpoints
+fn with_game_manager(
+    game_manager: Arc<RwLock<GameManager>>,
+) -> impl Filter<Extract = (Arc<RwLock<GameManager>>,), Error = Infallible> + Clone {
+    warp::any().map(move || game_manager.clone())
+}
+
+// Handler for `POST /create_game/{game_id}`
+async fn handle_create_game(
+    game_id: u32,
+    request: CreateGameRequest,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if game_manager.read().await.games.contains_key(&game_id) {
+        return Err(warp::reject::custom(ApiError::GameExists(game_id)));
+    }
+    let stake = request
+        .stake
+        .map(Stake::try_from)
+        .transpose()
+        .map_err(warp::reject::custom)?;
+    let transaction = Transaction::StartGame {
+        game_id,
+        time_control: request.time_control.map(Into::into),
+        stake,
+    };
+    let signed_tx = request
+        .signer
+        .into_signed(transaction, &game_manager)
+        .await
+        .map_err(warp::reject::custom)?;
+    let response = submit_transaction(composer_client, signed_tx, Some(game_id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+// Handler for `POST /games`
+async fn handle_create_assigned_game(
+    request: CreateGameRequest,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let stake = request
+        .stake
+        .map(Stake::try_from)
+        .transpose()
+        .map_err(warp::reject::custom)?;
+    let transaction = Transaction::CreateGame {
+        time_control: request.time_control.map(Into::into),
+        stake,
+    };
+    let signed_tx = request
+        .signer
+        .into_signed(transaction, &game_manager)
+        .await
+        .map_err(warp::reject::custom)?;
+    let response = submit_transaction(composer_client, signed_tx, None)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+// Handler for `POST /games/{game_id}/join`
+async fn handle_join_game(
+    game_id: u32,
+    request: SignedRequest,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let transaction = Transaction::JoinGame { game_id };
+    let signed_tx = request
+        .into_signed(transaction, &game_manager)
+        .await
+        .map_err(warp::reject::custom)?;
+    let response = submit_transaction(composer_client, signed_tx, Some(game_id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+// Handler for `POST /games/{game_id}/{action}`
+async fn handle_game_action(
+    game_id: u32,
+    action: String,
+    request: ActionRequest,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let transaction = match action.as_str() {
+        "resign" => Transaction::Resign { game_id },
+        "offer_draw" => Transaction::OfferDraw { game_id },
+        "accept_draw" => Transaction::AcceptDraw { game_id },
+        "decline_draw" => Transaction::DeclineDraw { game_id },
+        "claim_draw" => {
+            let claim = match request.claim.as_deref() {
+                Some("repetition") => DrawClaim::Repetition,
+                Some("fifty_moves") => DrawClaim::FiftyMoves,
+                _ => {
+                    return Err(warp::reject::custom(ApiError::InvalidRequest(
+                        "claim must be `repetition` or `fifty_moves`".to_string(),
+                    )))
+                }
+            };
+            Transaction::ClaimDraw { game_id, claim }
+        }
+        "abort" => Transaction::Abort { game_id },
+        _ => {
+            return Err(warp::reject::custom(ApiError::InvalidRequest(format!(
+                "unknown action `{}`",
+                action
+            ))))
+        }
+    };
+    let signed_tx = request
+        .signer
+        .into_signed(transaction, &game_manager)
+        .await
+        .map_err(warp::reject::custom)?;
+
+    {
+        let game_manager = game_manager.read().await;
+        let game_state = game_manager
+            .game_status(game_id)
+            .map_err(|_| warp::reject::custom(ApiError::GameNotFound(game_id)))?;
+        if game_state.is_over() {
+            return Err(warp::reject::custom(ApiError::GameFinished(game_id)));
+        }
+    }
+
+    let response = submit_transaction(composer_client, signed_tx, Some(game_id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+// Handler for `POST /games/{game_id}/moves`
+async fn handle_make_move(
+    game_id: u32,
+    request: MoveRequest,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let transaction = Transaction::MakeMove {
+        game_id,
+        move_san: request.mv.clone(),
+    };
+    let signed_tx = request
+        .signer
+        .into_signed(transaction, &game_manager)
+        .await
+        .map_err(warp::reject::custom)?;
+
+    // Validate against the latest executed state before paying for sequencing
+    {
+        let game_manager = game_manager.read().await;
+        let game_state = game_manager
+            .game_status(game_id)
+            .map_err(|_| warp::reject::custom(ApiError::GameNotFound(game_id)))?;
+        if game_state.is_over() {
+            return Err(warp::reject::custom(ApiError::GameFinished(game_id)));
+        }
+        if game_state.player_to_move() != Some(signed_tx.public_key) {
+            return Err(warp::reject::custom(ApiError::NotYourTurn(game_id)));
+        }
+        notation::parse_move(&game_state.game.current_position(), &request.mv)
+            .map_err(|reason| warp::reject::custom(ApiError::IllegalMove { game_id, reason }))?;
+    }
+
+    let response = submit_transaction(composer_client, signed_tx, Some(game_id))
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+// Encode a transaction and hand it to the composer for sequencing
+async fn submit_transaction(
+    mut composer: Composer,
+    signed_tx: SignedTransaction,
+    game_id: Option<u32>,
+) -> Result<SubmitResponse, ApiError> {
+    println!("encoding transaction: {:?}", signed_tx);
+    let encoded_transaction = signed_tx.encode();
+    println!(
+        "submitting transaction to sequencer... encoded transaction {:?}",
+        encoded_transaction
+    );
+    let tx_hash = transaction::transaction_hash(&encoded_transaction);
+    composer
+        .client
+        .submit_rollup_transaction(SubmitRollupTransactionRequest {
+            rollup_id: Some(composer.rollup_id.clone()),
+            data: encoded_transaction,
+        })
+        .await
+        .map_err(|status| ApiError::Composer(status.message().to_string()))?;
+    Ok(SubmitResponse {
+        tx_hash: hex::encode(tx_hash),
+        game_id,
+        status: "submitted",
+    })
+}
+
+// Handler for `GET /games`
+async fn handle_list_games(
+    game_manager: Arc<RwLock<GameManager>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let game_manager = game_manager.read().await;
+    let games: Vec<GameSummary> = game_manager
+        .games
+        .iter()
+        .map(|(game_id, game_state)| GameSummary::new(*game_id, game_state, &game_manager))
+        .collect();
+    Ok(warp::reply::json(&games))
+}
+
+// Handler for `GET /games/{game_id}` and `GET /game_status/{game_id}`
+async fn handle_get_game(
+    game_id: u32,
+    game_manager: Arc<RwLock<GameManager>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let game_manager = game_manager.read().await;
+    match game_manager.game_status(game_id) {
+        Ok(game_state) => Ok(warp::reply::json(&GameView::new(
+            game_id,
+            game_state,
+            &game_manager,
+        ))),
+        Err(_) => Err(warp::reject::custom(ApiError::GameNotFound(game_id))),
+    }
+}
+
+// Handler for `GET /games/{game_id}/proof`
+async fn handle_get_game_proof(
+    game_id: u32,
+    query: ProofQuery,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let app = app.read().await;
+    let height = query.height.unwrap_or(app.soft_height);
+    let (Some(block), Some(roots)) = (app.get_block(height), app.get_block_roots(height)) else {
+        return Err(warp::reject::custom(ApiError::BlockNotFound(height)));
+    };
+    // Game states are only kept from the firm block on
+    let game_manager = app.get_game_state(height).ok_or_else(|| {
+        warp::reject::custom(ApiError::ProofUnavailable(format!(
+            "no game state kept for block {}",
+            height
+        )))
+    })?;
+    if game_manager.game_status(game_id).is_err() {
+        return Err(warp::reject::custom(ApiError::GameNotFound(game_id)));
+    }
+    // The summary is built from the proven value, as a client checking the proof would
+    let (proof, game_state) = game_manager
+        .prove_game(game_id, block.parent_block_hash().clone(), *roots)
+        .and_then(|proof| {
+            let game_state = proof.verify_game(game_id, block.hash())?;
+            Ok((proof, game_state))
+        })
+        .map_err(|e| warp::reject::custom(ApiError::ProofUnavailable(e)))?;
+    Ok(warp::reply::json(&GameProofView {
+        game_id,
+        height,
+        timestamp_ms: proof.timestamp_ms,
+        block_hash: hex::encode(block.hash()),
+        parent_hash: hex::encode(&proof.parent_hash),
+        transactions_root: hex::encode(proof.transactions_root),
+        state_root: hex::encode(roots.state_root),
+        key: hex::encode(&proof.key),
+        value: hex::encode(&proof.value),
+        leaf_index: proof.proof.leaf_index,
+        leaf_count: proof.proof.leaf_count,
+        siblings: proof.proof.siblings.iter().map(hex::encode).collect(),
+        game: GameSummary::new(game_id, &game_state, game_manager),
+    }))
+}
+
+// Handler for `GET /accounts/{public_key}/balances`
+async fn handle_get_balances(
+    public_key: String,
+    game_manager: Arc<RwLock<GameManager>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut account: game::PublicKey = [0u8; 32];
+    hex::decode_to_slice(&public_key, &mut account).map_err(|e| {
+        warp::reject::custom(ApiError::InvalidRequest(format!("public_key: {}", e)))
+    })?;
+
+    let game_manager = game_manager.read().await;
+    let balances = game_manager
+        .accounts
+        .balances(&account)
+        .into_iter()
+        .map(|(asset, amount)| BalanceView {
+            asset,
+            amount: amount.to_string(),
+        })
+        .collect();
+    Ok(warp::reply::json(&BalancesView {
+        account: hex::encode(account),
+        balances,
+        as_of_height: game_manager.current_height,
+    }))
+}
+
+// Handler for `GET /accounts/{public_key}/nonce`
+async fn handle_get_nonce(
+    public_key: String,
+    game_manager: Arc<RwLock<GameManager>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut account: game::PublicKey = [0u8; 32];
+    hex::decode_to_slice(&public_key, &mut account).map_err(|e| {
+        warp::reject::custom(ApiError::InvalidRequest(format!("public_key: {}", e)))
+    })?;
+
+    let game_manager = game_manager.read().await;
+    Ok(warp::reply::json(&NonceView {
+        account: hex::encode(account),
+        nonce: game_manager.accounts.next_nonce(&account),
+        as_of_height: game_manager.current_height,
+    }))
+}
+
+// Handler for `POST /withdrawals`
+async fn handle_withdraw(
+    request: WithdrawRequest,
+    game_manager: Arc<RwLock<GameManager>>,
+    composer_client: Composer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let amount: u128 = request.amount.parse().map_err(|_| {
+        warp::reject::custom(ApiError::InvalidRequest(format!(
+            "amount: {} is not a valid amount",
+            request.amount
+        )))
+    })?;
+    let transaction = Transaction::Withdraw {
+        asset: request.asset.clone(),
+        amount,
+        destination: request.destination,
+        memo: request.memo,
+    };
+    let signed_tx = request
+        .signer
+        .into_signed(transaction, &game_manager)
+        .await
+        .map_err(warp::reject::custom)?;
+
+    // Validate against the latest executed state before paying for sequencing
+    let balance = game_manager
+        .read()
+        .await
+        .accounts
+        .balance(&signed_tx.public_key, &request.asset);
+    if balance < amount {
+        return Err(warp::reject::custom(ApiError::InvalidRequest(format!(
+            "insufficient {} balance: have {}, need {}",
+            request.asset, balance, amount
+        ))));
+    }
+
+    let response = submit_transaction(composer_client, signed_tx, None)
+        .await
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+// Handler for `GET /withdrawals`
+async fn handle_list_withdrawals(
+    query: WithdrawalsQuery,
+    app: Arc<RwLock<AppState>>,
+    game_manager: Arc<RwLock<GameManager>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Some(status) = &query.status {
+        if status != "pending" && status != "completed" {
+            return Err(warp::reject::custom(ApiError::InvalidRequest(format!(
+                "status: expected pending or completed, got {}",
+                status
+            ))));
+        }
+    }
+    // Same lock order as block execution: app state first, then game state
+    let app = app.read().await;
+    let game_manager = game_manager.read().await;
+    let withdrawals: Vec<WithdrawalView> = game_manager
+        .accounts
+        .withdrawals(
+            query.from_height.unwrap_or(0),
+            query.to_height.unwrap_or(u32::MAX),
+        )
+        .into_iter()
+        .filter(|withdrawal| {
+            query.status.as_deref().map_or(true, |status| {
+                status == withdrawal_status(withdrawal, app.firm_height)
+            })
+        })
+        .map(|withdrawal| WithdrawalView::new(withdrawal, app.firm_height))
+        .collect();
+    Ok(warp::reply::json(&withdrawals))
+}
+
+// Handler for `GET /receipts/{tx_hash}`
+async fn handle_get_receipt(
+    tx_hash: String,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut hash = [0u8; 32];
+    hex::decode_to_slice(&tx_hash, &mut hash)
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(format!("tx_hash: {}", e))))?;
+
+    let app = app.read().await;
+    match app.get_receipt(&hash) {
+        Some(receipt) => Ok(warp::reply::json(&ReceiptView::new(receipt))),
+        None => Err(warp::reject::custom(ApiError::ReceiptNotFound(tx_hash))),
+    }
+}
+
+// Handler for `GET /transactions/{tx_hash}`
+async fn handle_get_transaction(
+    tx_hash: String,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut hash = [0u8; 32];
+    hex::decode_to_slice(&tx_hash, &mut hash)
+        .map_err(|e| warp::reject::custom(ApiError::InvalidRequest(format!("tx_hash: {}", e))))?;
+
+    let app = app.read().await;
+    match app.get_transaction(&hash) {
+        Some((transaction, receipt)) => Ok(warp::reply::json(&TransactionView::new(
+            transaction,
+            receipt,
+        ))),
+        None => Err(warp::reject::custom(ApiError::ReceiptNotFound(tx_hash))),
+    }
+}
+
+// Handler for `GET /blocks/{height}`
+async fn handle_get_block(
+    height: u32,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let app = app.read().await;
+    match BlockView::new(&app, height) {
+        Some(block) => Ok(warp::reply::json(&block)),
+        None => Err(warp::reject::custom(ApiError::BlockNotFound(height))),
+    }
+}
+
+// Handler for `GET /blocks?from={height}&to={height}`, skipping heights not executed here
+async fn handle_list_blocks(
+    query: BlocksQuery,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if query.from > query.to {
+        return Err(warp::reject::custom(ApiError::InvalidRequest(format!(
+            "from {} is above to {}",
+            query.from, query.to
+        ))));
+    }
+    if query.to - query.from >= MAX_BLOCK_RANGE {
+        return Err(warp::reject::custom(ApiError::InvalidRequest(format!(
+            "at most {} blocks can be fetched at once",
+            MAX_BLOCK_RANGE
+        ))));
+    }
+    let app = app.read().await;
+    let blocks: Vec<BlockView> = (query.from..=query.to)
+        .filter_map(|height| BlockView::new(&app, height))
+        .collect();
+    Ok(warp::reply::json(&blocks))
+}
+
+// Handler for `GET /blocks/{height}/receipts`
+async fn handle_get_block_results(
+    height: u32,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let app = app.read().await;
+    match app.get_block_body(height) {
+        Some(body) => Ok(warp::reply::json(&BlockResultsView::new(height, body))),
+        None => Err(warp::reject::custom(ApiError::BlockNotFound(height))),
+    }
+}
+
+// Handler for `GET /blocks/{height}/state_root`
+async fn handle_get_state_root(
+    height: u32,
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let app = app.read().await;
+    let (Some(block), Some(roots)) = (app.get_block(height), app.get_block_roots(height)) else {
+        return Err(warp::reject::custom(ApiError::BlockNotFound(height)));
+    };
+    Ok(warp::reply::json(&StateRootView {
+        height,
+        timestamp_ms: game::timestamp_ms(&block.timestamp()),
+        hash: hex::encode(block.hash()),
+        parent_hash: hex::encode(block.parent_block_hash()),
+        transactions_root: hex::encode(roots.transactions_root),
+        state_root: hex::encode(roots.state_root),
+    }))
+}
+
+// Handler for `GET /snapshot`
+async fn handle_get_snapshot(
+    app: Arc<RwLock<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // Only copying the state holds the lock; execution is not blocked while it is serialized
+    let snapshot = Snapshot::export(&*app.read().await)
+        .map_err(|e| warp::reject::custom(ApiError::SnapshotUnavailable(e)))?;
+    Ok(warp::reply::json(&snapshot))
+}
+
+// Turn rejections into JSON `ErrorResponse` bodies
+async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
+    let (status, code, message) = if let Some(api_error) = err.find::<ApiError>() {
+        (
+            api_error.status(),
+            api_error.code().to_string(),
+            api_error.to_string(),
+        )
+    } else if err.is_not_found() {
+        (
+            StatusCode::NOT_FOUND,
+            "not_found".to_string(),
+            "route not found".to_string(),
+        )
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed".to_string(),
+            "method not allowed".to_string(),
+        )
+    } else {
+        (
+            StatusCode::BAD_REQUEST,
+            "bad_request".to_string(),
+            format!("{:?}", err),
+        )
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ErrorResponse { code, message }),
+        status,
+    ))
+}
+
+fn withdrawal_status(withdrawal: &Withdrawal, firm_height: u32) -> &'static str {
+    if withdrawal.height <= firm_height {
+        "completed"
+    } else {
+        "pending"
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+fn status_name(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::WaitingForOpponent => "waiting_for_opponent",
+        GameStatus::Active => "active",
+        GameStatus::Finished { .. } => "finished",
+        GameStatus::Aborted => "aborted",
+    }
+}
+
+fn outcome(status: GameStatus) -> Option<(Outcome, EndReason)> {
+    match status {
+        GameStatus::Finished { result, reason } => Some((result, reason)),
+        _ => None,
+    }
+}
+
+fn outcome_name(result: Outcome) -> &'static str {
+    match result {
+        Outcome::WhiteWins => "white_wins",
+        Outcome::BlackWins => "black_wins",
+        Outcome::Draw => "draw",
+    }
+}
+
+fn reason_name(reason: EndReason) -> &'static str {
+    match reason {
+        EndReason::Checkmate => "checkmate",
+        EndReason::Stalemate => "stalemate",
+        EndReason::Resignation => "resignation",
+        EndReason::Agreement => "agreement",
+        EndReason::Timeout => "timeout",
+        EndReason::Repetition => "repetition",
+        EndReason::FiftyMoves => "fifty_moves",
+    }
+}
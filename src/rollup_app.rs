@@ -1,38 +1,59 @@
+use crate::game::{BlockRoots, GameManager};
+use crate::receipts::{BlockBody, BlockTransaction, Receipt};
 use astria_core::{execution::v1::Block, Protobuf};
 use astria_sequencer_client::{
     tendermint::{serializers::timestamp, time::ParseTimestamp},
     tendermint_proto::google::protobuf::Timestamp,
 };
 use bytes::Bytes;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug)]
 pub struct AppState {
     pub blocks: HashMap<u32, Block>, // store blocks indexed by block number
+    pub block_hashes: HashMap<Bytes, u32>, // block numbers indexed by block hash
     pub soft_height: u32,            // current soft height
     pub firm_height: u32,
     pub celestia_height: u64,
+    pub bodies: HashMap<u32, BlockBody>, // block bodies indexed by block number
+    pub tx_index: HashMap<[u8; 32], (u32, usize)>, // receipt location indexed by tx hash
+    pub block_roots: HashMap<u32, BlockRoots>, // roots committed by each executed block
+    pub game_states: BTreeMap<u32, GameManager>, // game state after each block from firm to soft
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    // State holding only the genesis block
+    pub fn new(
+        genesis_hash: Bytes,
+        genesis_timestamp: pbjson_types::Timestamp,
+        celestia_height: u64,
+    ) -> Self {
         let block = astria_core::generated::execution::v1::Block {
             number: 0,
-            hash: Bytes::from_static(&[69_u8; 32]),
+            hash: genesis_hash,
             parent_block_hash: Bytes::new(),
-            timestamp: Some(pbjson_types::Timestamp {
-                seconds: 0,
-                nanos: 0,
-            }),
+            timestamp: Some(genesis_timestamp),
         };
-        let mut blocks: HashMap<u32, Block> = HashMap::new();
-        blocks.insert(0, Block::try_from_raw(block).unwrap());
-        AppState {
-            blocks: blocks,
+        let mut app = AppState {
+            blocks: HashMap::new(),
+            block_hashes: HashMap::new(),
             soft_height: 0,
             firm_height: 0,
-            celestia_height: 2,
-        }
+            celestia_height,
+            bodies: HashMap::new(),
+            tx_index: HashMap::new(),
+            block_roots: HashMap::new(),
+            game_states: BTreeMap::new(),
+        };
+        app.insert_block(Block::try_from_raw(block).unwrap());
+        app
+    }
+
+    // Store a block and index it by hash, without touching the heights
+    pub fn insert_block(&mut self, block: Block) {
+        self.block_hashes
+            .insert(block.hash().clone(), block.number());
+        self.blocks.insert(block.number(), block);
     }
 
     // Add a new block (it must be greater than both current heights)
@@ -47,7 +68,7 @@ impl AppState {
         }
 
         // Insert the new block into the HashMap
-        self.blocks.insert(block.number(), block);
+        self.insert_block(block);
 
         // Update the soft height to the latest block number
         self.soft_height = block_height;
@@ -71,11 +92,101 @@ impl AppState {
         block.parent_block_hash().clone()
     }
 
+    // Store the body of an executed block and index its receipts by tx hash.
+    // A receipt of an applied transaction is never shadowed by a rejected replay of it.
+    pub fn add_body(&mut self, block_number: u32, body: BlockBody) {
+        for (position, receipt) in body.receipts.iter().enumerate() {
+            let applied = self
+                .get_receipt(&receipt.tx_hash)
+                .is_some_and(|existing| existing.error.is_none());
+            if !applied {
+                self.tx_index
+                    .insert(receipt.tx_hash, (block_number, position));
+            }
+        }
+        self.bodies.insert(block_number, body);
+    }
+
+    // Retrieve a receipt by transaction hash
+    pub fn get_receipt(&self, tx_hash: &[u8; 32]) -> Option<&Receipt> {
+        let (block_number, position) = self.tx_index.get(tx_hash)?;
+        self.bodies.get(block_number)?.receipts.get(*position)
+    }
+
+    // Retrieve a transaction as sequenced together with its receipt, by transaction hash
+    pub fn get_transaction(&self, tx_hash: &[u8; 32]) -> Option<(&BlockTransaction, &Receipt)> {
+        let (block_number, position) = self.tx_index.get(tx_hash)?;
+        let body = self.bodies.get(block_number)?;
+        Some((
+            body.transactions.get(*position)?,
+            body.receipts.get(*position)?,
+        ))
+    }
+
+    // Retrieve the transactions, events and receipts of a block
+    pub fn get_block_body(&self, block_number: u32) -> Option<&BlockBody> {
+        self.bodies.get(&block_number)
+    }
+
+    // Retrieve the transactions and state roots of an executed block, None for genesis
+    pub fn get_block_roots(&self, block_number: u32) -> Option<&BlockRoots> {
+        self.block_roots.get(&block_number)
+    }
+
     // Retrieve a block by its number
     pub fn get_block(&self, block_number: u32) -> Option<&Block> {
         self.blocks.get(&block_number)
     }
 
+    // Record the game state resulting from the block at `height`
+    pub fn add_game_state(&mut self, height: u32, game_manager: GameManager) {
+        self.game_states.insert(height, game_manager);
+    }
+
+    // Retrieve the game state as of the block at `height`, if it is still kept
+    pub fn get_game_state(&self, height: u32) -> Option<&GameManager> {
+        self.game_states.get(&height)
+    }
+
+    // Forget game states below the firm height, which can no longer be rolled back to
+    pub fn prune_game_states(&mut self) {
+        let firm_height = self.firm_height;
+        self.game_states.retain(|height, _| *height >= firm_height);
+    }
+
+    // Roll back to the block at `height`, dropping every later block together with its
+    // receipts, roots and game state. Returns the heights that were dropped.
+    pub fn truncate(&mut self, height: u32) -> Vec<u32> {
+        let mut reverted: Vec<u32> = self
+            .blocks
+            .keys()
+            .copied()
+            .filter(|block_number| *block_number > height)
+            .collect();
+        reverted.sort();
+        for block_number in &reverted {
+            if let Some(block) = self.blocks.remove(block_number) {
+                self.block_hashes.remove(block.hash());
+            }
+            self.block_roots.remove(block_number);
+        }
+        self.game_states
+            .retain(|block_number, _| *block_number <= height);
+        self.bodies
+            .retain(|block_number, _| *block_number <= height);
+        self.tx_index
+            .retain(|_, (block_number, _)| *block_number <= height);
+        self.soft_height = self.soft_height.min(height);
+        reverted
+    }
+
+    // Retrieve a block by its hash
+    pub fn get_block_by_hash(&self, hash: &[u8]) -> Option<&Block> {
+        self.block_hashes
+            .get(hash)
+            .and_then(|block_number| self.blocks.get(block_number))
+    }
+
     pub fn new_block(
         &mut self,
         parent_hash: Bytes,
@@ -94,3 +205,80 @@ impl AppState {
         block
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(height: u32) -> Bytes {
+        Bytes::from(vec![height as u8; 32])
+    }
+
+    fn receipt(height: u32) -> Receipt {
+        Receipt {
+            tx_hash: [height as u8; 32],
+            height,
+            index: 0,
+            game_id: None,
+            error: None,
+            events: Vec::new(),
+        }
+    }
+
+    // Blocks 1 to `height` on top of genesis, each with one receipt and a game state
+    fn chain(height: u32) -> AppState {
+        let mut app = AppState::new(hash(0), pbjson_types::Timestamp::default(), 0);
+        app.add_game_state(0, GameManager::default());
+        for number in 1..=height {
+            app.new_block(
+                hash(number - 1),
+                hash(number),
+                number,
+                pbjson_types::Timestamp::default(),
+            );
+            app.add_body(
+                number,
+                BlockBody {
+                    receipts: vec![receipt(number)],
+                    ..BlockBody::default()
+                },
+            );
+            app.add_game_state(number, GameManager::default());
+        }
+        app
+    }
+
+    #[test]
+    fn truncate_drops_later_blocks() {
+        let mut app = chain(3);
+        assert_eq!(app.truncate(1), vec![2, 3]);
+        assert_eq!(app.soft_height, 1);
+        assert!(app.get_block(1).is_some());
+        for number in [2, 3] {
+            assert!(app.get_block(number).is_none());
+            assert!(app.get_block_by_hash(&hash(number)).is_none());
+            assert!(app.get_block_body(number).is_none());
+            assert!(app.get_game_state(number).is_none());
+            assert!(app.get_receipt(&[number as u8; 32]).is_none());
+        }
+        assert!(app.get_receipt(&[1u8; 32]).is_some());
+        assert!(app.get_game_state(1).is_some());
+    }
+
+    #[test]
+    fn truncate_at_the_soft_height_changes_nothing() {
+        let mut app = chain(2);
+        assert!(app.truncate(2).is_empty());
+        assert_eq!(app.soft_height, 2);
+        assert!(app.get_block(2).is_some());
+    }
+
+    #[test]
+    fn rolled_back_heights_can_be_executed_again() {
+        let mut app = chain(3);
+        app.truncate(1);
+        app.new_block(hash(1), hash(20), 2, pbjson_types::Timestamp::default());
+        assert_eq!(app.soft_height, 2);
+        assert_eq!(app.get_block_by_hash(&hash(20)).map(Block::number), Some(2));
+    }
+}
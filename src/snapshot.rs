@@ -0,0 +1,213 @@
+use crate::game::{self, GameManager};
+use crate::genesis::Genesis;
+use crate::rollup_app::AppState;
+use crate::storage::StoredBlock;
+use astria_core::Protobuf;
+use color_eyre::eyre::{self, eyre, WrapErr as _};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Bumped whenever the snapshot layout changes; older snapshots are refused
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// Rollup state at a firm height, enough to start a node without replaying from genesis.
+// Block bodies and receipts are not included.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub height: u32, // Firm height the state was taken at
+    pub celestia_height: u64,
+    pub blocks: Vec<StoredBlock>, // Headers from block 1 to `height`, in order
+    pub state: GameManager,       // Game state after the block at `height`
+}
+
+impl Snapshot {
+    // Take a snapshot of the state at the firm height
+    pub fn export(app: &AppState) -> Result<Self, String> {
+        let height = app.firm_height;
+        let state = app
+            .get_game_state(height)
+            .cloned()
+            .ok_or_else(|| format!("no game state kept for firm block {}", height))?;
+        let mut blocks = Vec::with_capacity(height as usize);
+        for number in 1..=height {
+            let (Some(block), Some(roots)) = (app.get_block(number), app.get_block_roots(number))
+            else {
+                return Err(format!("block {} is not stored", number));
+            };
+            blocks.push(StoredBlock::from_raw(&block.to_owned().into_raw(), *roots));
+        }
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            height,
+            celestia_height: app.celestia_height,
+            blocks,
+            state,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        std::fs::write(path, serde_json::to_vec(self)?)
+            .wrap_err(format!("failed writing snapshot {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let data =
+            std::fs::read(path).wrap_err(format!("failed reading snapshot {}", path.display()))?;
+        serde_json::from_slice(&data).wrap_err("failed decoding snapshot")
+    }
+
+    // Check that the headers chain up from genesis, that the state runs with the genesis
+    // parameters and that it hashes to the state root committed by the block at `height`
+    pub fn verify(&self, genesis: &Genesis) -> eyre::Result<()> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(eyre!(
+                "unsupported snapshot version {}, expected {}",
+                self.version,
+                SNAPSHOT_VERSION
+            ));
+        }
+        if self.blocks.len() != self.height as usize {
+            return Err(eyre!(
+                "snapshot at height {} holds {} blocks",
+                self.height,
+                self.blocks.len()
+            ));
+        }
+        if self.state.params != genesis.params()? {
+            return Err(eyre!(
+                "snapshot protocol parameters differ from those in the genesis file"
+            ));
+        }
+        let mut parent_hash = genesis.block_hash()?;
+        for (block, number) in self.blocks.iter().zip(1..) {
+            if block.number != number {
+                return Err(eyre!(
+                    "expected block {}, found block {}",
+                    number,
+                    block.number
+                ));
+            }
+            if block.parent_block_hash != parent_hash {
+                return Err(eyre!("block {} does not build on its predecessor", number));
+            }
+            let timestamp_ms = game::timestamp_ms(&pbjson_types::Timestamp {
+                seconds: block.timestamp_seconds,
+                nanos: block.timestamp_nanos,
+            });
+            if block.hash != game::block_hash(&parent_hash, number, timestamp_ms, &block.roots) {
+                return Err(eyre!("block {} hash does not match its roots", number));
+            }
+            parent_hash = block.hash.clone();
+        }
+        let expected_root = match self.blocks.last() {
+            Some(block) => block.roots.state_root,
+            None => genesis.game_manager()?.state_root(),
+        };
+        if self.state.state_root() != expected_root {
+            return Err(eyre!(
+                "snapshot state root {} does not match the root {} of block {}",
+                hex::encode(self.state.state_root()),
+                hex::encode(expected_root),
+                self.height
+            ));
+        }
+        Ok(())
+    }
+
+    // App and game state with the snapshot's block soft and firm; only call after `verify`
+    pub fn into_state(self, genesis: &Genesis) -> eyre::Result<(AppState, GameManager)> {
+        let mut app = genesis.app_state()?;
+        for block in self.blocks {
+            app.block_roots.insert(block.number, block.roots);
+            app.insert_block(block.into_block()?);
+        }
+        app.soft_height = self.height;
+        app.firm_height = self.height;
+        app.celestia_height = self.celestia_height;
+        app.add_game_state(self.height, self.state.clone());
+        Ok((app, self.state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::genesis_state;
+    use crate::execution_service::execute_request;
+    use astria_core::generated::execution::v1 as execution;
+
+    fn genesis(tx_fee: &str) -> Genesis {
+        serde_json::from_value(serde_json::json!({
+            "rollup_name": "chess-test",
+            "sequencer_genesis_block_height": 1,
+            "celestia_block_variance": 10,
+            "base_celestia_height": 1,
+            "block_hash": hex::encode([0x45u8; 32]),
+            "params": {"fee_asset": "nria", "tx_fee": tx_fee},
+            "games": [{"game_id": 1, "white": hex::encode([1u8; 32])}]
+        }))
+        .unwrap()
+    }
+
+    // Snapshot at firm block 2 of a chain of three empty blocks
+    fn firm_snapshot(genesis: &Genesis) -> Snapshot {
+        let (mut app, _) = genesis_state(genesis).unwrap();
+        for seconds in 1..=3 {
+            let request = execution::ExecuteBlockRequest {
+                prev_block_hash: app.get_block(app.soft_height).unwrap().hash().clone(),
+                transactions: Vec::new(),
+                timestamp: Some(pbjson_types::Timestamp { seconds, nanos: 0 }),
+            };
+            execute_request(&app, request).unwrap().record(&mut app);
+        }
+        app.set_firm_height(2);
+        Snapshot::export(&app).unwrap()
+    }
+
+    #[test]
+    fn exported_snapshots_verify() {
+        let genesis = genesis("0");
+        let snapshot = firm_snapshot(&genesis);
+        assert_eq!(snapshot.height, 2);
+        assert_eq!(snapshot.blocks.len(), 2);
+        snapshot.verify(&genesis).unwrap();
+        let (app, game_manager) = snapshot.into_state(&genesis).unwrap();
+        assert_eq!((app.soft_height, app.firm_height), (2, 2));
+        assert!(game_manager.game_status(1).is_ok());
+    }
+
+    #[test]
+    fn tampered_state_is_rejected() {
+        let genesis = genesis("0");
+        let mut snapshot = firm_snapshot(&genesis);
+        snapshot.state.next_game_id += 1;
+        assert!(snapshot.verify(&genesis).is_err());
+
+        let mut snapshot = firm_snapshot(&genesis);
+        snapshot.state.games.clear();
+        assert!(snapshot.verify(&genesis).is_err());
+    }
+
+    #[test]
+    fn tampered_headers_are_rejected() {
+        let genesis = genesis("0");
+        let mut snapshot = firm_snapshot(&genesis);
+        snapshot.blocks[0].timestamp_seconds += 1;
+        assert!(snapshot.verify(&genesis).is_err());
+
+        let mut snapshot = firm_snapshot(&genesis);
+        snapshot.blocks[1].roots.transactions_root = [1u8; 32];
+        assert!(snapshot.verify(&genesis).is_err());
+
+        let mut snapshot = firm_snapshot(&genesis);
+        snapshot.blocks.pop();
+        assert!(snapshot.verify(&genesis).is_err());
+    }
+
+    #[test]
+    fn params_must_match_genesis() {
+        let snapshot = firm_snapshot(&genesis("0"));
+        assert!(snapshot.verify(&genesis("1")).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::game::{BlockRoots, GameManager};
+use crate::genesis::Genesis;
+use crate::receipts::BlockBody;
+use crate::rollup_app::AppState;
+use astria_core::generated::execution::v1 as execution;
+use astria_core::{execution::v1::Block, Protobuf};
+use bytes::Bytes;
+use color_eyre::eyre::{self, eyre, WrapErr as _};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Keys of the embedded database. Per-block entries are suffixed with the big-endian height.
+const BLOCK_PREFIX: &[u8] = b"block/";
+const BODY_PREFIX: &[u8] = b"body/";
+const STATE_PREFIX: &[u8] = b"state/"; // game state after each block from firm to soft
+const COMMITMENT_KEY: &[u8] = b"commitment";
+
+// Durable rollup state, kept in a sled database at `Config::db_filepath`
+#[derive(Clone)]
+pub struct Storage {
+    db: sled::Db,
+}
+
+// Block header together with the roots its hash commits to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredBlock {
+    pub number: u32,
+    pub hash: Bytes,
+    pub parent_block_hash: Bytes,
+    pub timestamp_seconds: i64,
+    pub timestamp_nanos: i32,
+    pub roots: BlockRoots,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CommitmentHeights {
+    soft_height: u32,
+    firm_height: u32,
+    celestia_height: u64,
+}
+
+impl Storage {
+    pub fn open(path: &Path) -> eyre::Result<Self> {
+        let db =
+            sled::open(path).wrap_err(format!("failed opening database at {}", path.display()))?;
+        Ok(Storage { db })
+    }
+
+    // Restore the app and game state, None if nothing has been committed yet
+    pub fn load(&self, genesis: &Genesis) -> eyre::Result<Option<(AppState, GameManager)>> {
+        let Some(commitment) = self.db.get(COMMITMENT_KEY)? else {
+            return Ok(None);
+        };
+        let commitment: CommitmentHeights =
+            serde_json::from_slice(&commitment).wrap_err("failed decoding commitment heights")?;
+
+        // Start from genesis and layer every committed block on top
+        let mut app = genesis.app_state()?;
+        for entry in self.db.scan_prefix(BLOCK_PREFIX) {
+            let (_, value) = entry?;
+            let stored: StoredBlock =
+                serde_json::from_slice(&value).wrap_err("failed decoding block")?;
+            app.block_roots.insert(stored.number, stored.roots);
+            app.insert_block(stored.into_block()?);
+        }
+        for entry in self.db.scan_prefix(BODY_PREFIX) {
+            let (key, value) = entry?;
+            let body: BlockBody =
+                serde_json::from_slice(&value).wrap_err("failed decoding block body")?;
+            app.add_body(key_height(BODY_PREFIX, &key)?, body);
+        }
+        for entry in self.db.scan_prefix(STATE_PREFIX) {
+            let (key, value) = entry?;
+            let height = key_height(STATE_PREFIX, &key)?;
+            let game_manager: GameManager =
+                serde_json::from_slice(&value).wrap_err("failed decoding game state")?;
+            app.add_game_state(height, game_manager);
+        }
+        app.soft_height = commitment.soft_height;
+        app.firm_height = commitment.firm_height;
+        app.celestia_height = commitment.celestia_height;
+        let game_manager = app
+            .get_game_state(app.soft_height)
+            .cloned()
+            .ok_or_else(|| eyre!("no game state stored for soft height {}", app.soft_height))?;
+        // Parameters are part of the state, a changed genesis file cannot alter them
+        if game_manager.params != genesis.params()? {
+            return Err(eyre!(
+                "protocol parameters in the database differ from those in the genesis file"
+            ));
+        }
+
+        println!(
+            "restored state from database: soft height {}, firm height {}",
+            app.soft_height, app.firm_height
+        );
+        Ok(Some((app, game_manager)))
+    }
+
+    // Atomically write an executed block, its body, the resulting game state and heights,
+    // removing the blocks at `reverted` heights that it replaces. `app` is the state before
+    // the block, whose soft height the block becomes.
+    pub fn commit_block(
+        &self,
+        app: &AppState,
+        block: &execution::Block,
+        roots: &BlockRoots,
+        body: &BlockBody,
+        game_manager: &GameManager,
+        reverted: &[u32],
+    ) -> eyre::Result<()> {
+        let height = block.number;
+        let mut batch = sled::Batch::default();
+        for reverted_height in reverted {
+            batch.remove(height_key(BLOCK_PREFIX, *reverted_height));
+            batch.remove(height_key(BODY_PREFIX, *reverted_height));
+            batch.remove(height_key(STATE_PREFIX, *reverted_height));
+        }
+        batch.insert(
+            height_key(BLOCK_PREFIX, height),
+            serde_json::to_vec(&StoredBlock::from_raw(block, *roots))?,
+        );
+        batch.insert(height_key(BODY_PREFIX, height), serde_json::to_vec(body)?);
+        batch.insert(
+            height_key(STATE_PREFIX, height),
+            serde_json::to_vec(game_manager)?,
+        );
+        let heights = CommitmentHeights {
+            soft_height: height,
+            ..commitment_heights(app)
+        };
+        batch.insert(COMMITMENT_KEY, serde_json::to_vec(&heights)?);
+        self.db
+            .apply_batch(batch)
+            .wrap_err(format!("failed committing block {}", height))?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Persist updated soft, firm and celestia heights, dropping game states below firm
+    pub fn commit_heights(
+        &self,
+        soft_height: u32,
+        firm_height: u32,
+        celestia_height: u64,
+    ) -> eyre::Result<()> {
+        let mut batch = sled::Batch::default();
+        let below_firm = height_key(STATE_PREFIX, 0)..height_key(STATE_PREFIX, firm_height);
+        for entry in self.db.range(below_firm) {
+            let (key, _) = entry?;
+            batch.remove(key);
+        }
+        let heights = CommitmentHeights {
+            soft_height,
+            firm_height,
+            celestia_height,
+        };
+        batch.insert(COMMITMENT_KEY, serde_json::to_vec(&heights)?);
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Write the blocks, game states and heights of a state restored from a snapshot
+    pub fn commit_snapshot(&self, app: &AppState) -> eyre::Result<()> {
+        let mut batch = sled::Batch::default();
+        for height in 1..=app.soft_height {
+            let (Some(block), Some(roots)) = (app.get_block(height), app.get_block_roots(height))
+            else {
+                return Err(eyre!("restored state is missing block {}", height));
+            };
+            batch.insert(
+                height_key(BLOCK_PREFIX, height),
+                serde_json::to_vec(&StoredBlock::from_raw(&block.to_owned().into_raw(), *roots))?,
+            );
+        }
+        for (height, game_manager) in &app.game_states {
+            batch.insert(
+                height_key(STATE_PREFIX, *height),
+                serde_json::to_vec(game_manager)?,
+            );
+        }
+        batch.insert(
+            COMMITMENT_KEY,
+            serde_json::to_vec(&commitment_heights(app))?,
+        );
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Persist the game state as of the block at `height`
+    pub fn commit_game_state(&self, height: u32, game_manager: &GameManager) -> eyre::Result<()> {
+        self.db.insert(
+            height_key(STATE_PREFIX, height),
+            serde_json::to_vec(game_manager)?,
+        )?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+impl StoredBlock {
+    pub fn from_raw(raw: &execution::Block, roots: BlockRoots) -> Self {
+        let timestamp = raw.timestamp.clone().unwrap_or_default();
+        StoredBlock {
+            number: raw.number,
+            hash: raw.hash.clone(),
+            parent_block_hash: raw.parent_block_hash.clone(),
+            timestamp_seconds: timestamp.seconds,
+            timestamp_nanos: timestamp.nanos,
+            roots,
+        }
+    }
+
+    pub fn into_block(self) -> eyre::Result<Block> {
+        let raw = execution::Block {
+            number: self.number,
+            hash: self.hash,
+            parent_block_hash: self.parent_block_hash,
+            timestamp: Some(pbjson_types::Timestamp {
+                seconds: self.timestamp_seconds,
+                nanos: self.timestamp_nanos,
+            }),
+        };
+        Block::try_from_raw(raw).map_err(|e| eyre!("invalid stored block {}: {}", self.number, e))
+    }
+}
+
+fn commitment_heights(app: &AppState) -> CommitmentHeights {
+    CommitmentHeights {
+        soft_height: app.soft_height,
+        firm_height: app.firm_height,
+        celestia_height: app.celestia_height,
+    }
+}
+
+fn height_key(prefix: &[u8], height: u32) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend_from_slice(&height.to_be_bytes());
+    key
+}
+
+fn key_height(prefix: &[u8], key: &[u8]) -> eyre::Result<u32> {
+    let height: [u8; 4] = key[prefix.len()..]
+        .try_into()
+        .map_err(|_| eyre!("malformed database key {}", hex::encode(key)))?;
+    Ok(u32::from_be_bytes(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::genesis_state;
+    use crate::execution_service::{execute_request, Commitment};
+
+    fn genesis() -> Genesis {
+        serde_json::from_value(serde_json::json!({
+            "rollup_name": "chess-test",
+            "sequencer_genesis_block_height": 1,
+            "celestia_block_variance": 10,
+            "base_celestia_height": 1,
+            "block_hash": hex::encode([0x45u8; 32]),
+            "balances": [{"account": hex::encode([1u8; 32]), "asset": "nria", "amount": "100"}],
+            "games": [{
+                "game_id": 1,
+                "white": hex::encode([1u8; 32]),
+                "black": hex::encode([2u8; 32]),
+                "moves": ["e2e4"]
+            }]
+        }))
+        .unwrap()
+    }
+
+    // Execute and persist an empty block on top of the block at `parent`
+    fn execute(storage: &Storage, app: &mut AppState, parent: u32, seconds: i64) {
+        let request = execution::ExecuteBlockRequest {
+            prev_block_hash: app.get_block(parent).unwrap().hash().clone(),
+            transactions: Vec::new(),
+            timestamp: Some(pbjson_types::Timestamp { seconds, nanos: 0 }),
+        };
+        let execution = execute_request(app, request).unwrap();
+        storage
+            .commit_block(
+                app,
+                &execution.block,
+                &execution.executed.roots,
+                &execution.executed.body,
+                &execution.game_manager,
+                &execution.reverted,
+            )
+            .unwrap();
+        execution.record(app);
+    }
+
+    fn block_hashes(app: &AppState) -> Vec<Bytes> {
+        (0..=app.soft_height)
+            .map(|height| app.get_block(height).unwrap().hash().clone())
+            .collect()
+    }
+
+    #[test]
+    fn committed_blocks_are_restored() {
+        let genesis = genesis();
+        let storage = Storage {
+            db: sled::Config::new().temporary(true).open().unwrap(),
+        };
+        assert!(storage.load(&genesis).unwrap().is_none());
+        let (mut app, game_manager) = genesis_state(&genesis).unwrap();
+        storage.commit_game_state(0, &game_manager).unwrap();
+        for height in 1..=3 {
+            execute(&storage, &mut app, height - 1, i64::from(height));
+        }
+        // Re-executing block 3 replaces it
+        let replaced = app.get_block(3).unwrap().hash().clone();
+        execute(&storage, &mut app, 2, 10);
+        let commitment = Commitment {
+            soft_height: 3,
+            firm_height: 2,
+            celestia_height: 5,
+        };
+        storage.commit_heights(3, 2, 5).unwrap();
+        commitment.record(&mut app);
+
+        let (restored, game_manager) = storage.load(&genesis).unwrap().unwrap();
+        assert_eq!(
+            (
+                restored.soft_height,
+                restored.firm_height,
+                restored.celestia_height
+            ),
+            (3, 2, 5)
+        );
+        assert_eq!(block_hashes(&restored), block_hashes(&app));
+        assert!(restored.get_block_by_hash(&replaced).is_none());
+        for height in 1..=3 {
+            assert_eq!(
+                restored.get_block_roots(height).unwrap().state_root,
+                app.get_block_roots(height).unwrap().state_root
+            );
+            assert_eq!(
+                serde_json::to_vec(restored.get_block_body(height).unwrap()).unwrap(),
+                serde_json::to_vec(app.get_block_body(height).unwrap()).unwrap()
+            );
+        }
+        // Only the states from firm to soft are kept
+        assert_eq!(
+            restored.game_states.keys().copied().collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(
+            game_manager.state_root(),
+            app.get_game_state(3).unwrap().state_root()
+        );
+        assert!(game_manager.game_status(1).is_ok());
+    }
+}
@@ -0,0 +1,569 @@
+use crate::game::{PublicKey, Stake, TimeControl};
+use astria_core::crypto::{Signature, VerificationKey};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transaction {
+    StartGame {
+        game_id: u32,
+        time_control: Option<TimeControl>,
+        stake: Option<Stake>, // Escrowed from the creator, matched by the opponent on join
+    },
+    MakeMove {
+        game_id: u32,
+        move_san: String,
+    }, // SAN (Standard Algebraic Notation) or UCI move
+    JoinGame {
+        game_id: u32,
+    },
+    CreateGame {
+        time_control: Option<TimeControl>,
+        stake: Option<Stake>,
+    }, // Like StartGame, but the rollup assigns the id
+    Resign {
+        game_id: u32,
+    },
+    OfferDraw {
+        game_id: u32,
+    },
+    AcceptDraw {
+        game_id: u32,
+    },
+    DeclineDraw {
+        game_id: u32,
+    },
+    ClaimDraw {
+        game_id: u32,
+        claim: DrawClaim,
+    },
+    Abort {
+        game_id: u32,
+    }, // Only allowed before the first move
+    Withdraw {
+        asset: String,
+        amount: u128,
+        destination: String, // Sequencer address receiving the funds
+        memo: String,
+    },
+}
+
+// Grounds on which a player may claim a draw without the opponent's agreement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawClaim {
+    Repetition, // Threefold repetition of the current position
+    FiftyMoves, // Fifty moves by each side without a capture or pawn move
+}
+
+// Envelope carrying a transaction together with its signer's key, signature and nonce
+#[derive(Debug, Clone)]
+pub struct SignedTransaction {
+    pub public_key: PublicKey,
+    pub signature: [u8; 64],
+    pub nonce: u64,
+    pub transaction: Transaction,
+}
+
+impl Transaction {
+    // Game an existing-game transaction refers to
+    pub fn game_id(&self) -> Option<u32> {
+        match self {
+            Transaction::StartGame { game_id, .. }
+            | Transaction::MakeMove { game_id, .. }
+            | Transaction::JoinGame { game_id }
+            | Transaction::Resign { game_id }
+            | Transaction::OfferDraw { game_id }
+            | Transaction::AcceptDraw { game_id }
+            | Transaction::DeclineDraw { game_id }
+            | Transaction::ClaimDraw { game_id, .. }
+            | Transaction::Abort { game_id } => Some(*game_id),
+            Transaction::CreateGame { .. } | Transaction::Withdraw { .. } => None,
+        }
+    }
+
+    // Encode the transaction into bytes
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::new();
+
+        match self {
+            Transaction::StartGame {
+                game_id,
+                time_control,
+                stake,
+            } => {
+                buffer.put_u8(0); // Indicator for StartGame
+                buffer.put_u32(*game_id);
+                put_game_terms(&mut buffer, time_control, stake);
+            }
+            Transaction::MakeMove { game_id, move_san } => {
+                buffer.put_u8(1); // Indicator for MakeMove
+                buffer.put_u32(*game_id);
+                buffer.put_u32(move_san.len() as u32);
+                buffer.put_slice(move_san.as_bytes());
+            }
+            Transaction::JoinGame { game_id } => {
+                buffer.put_u8(2); // Indicator for JoinGame
+                buffer.put_u32(*game_id);
+            }
+            Transaction::CreateGame {
+                time_control,
+                stake,
+            } => {
+                buffer.put_u8(3); // Indicator for CreateGame
+                put_game_terms(&mut buffer, time_control, stake);
+            }
+            Transaction::Resign { game_id } => {
+                buffer.put_u8(4); // Indicator for Resign
+                buffer.put_u32(*game_id);
+            }
+            Transaction::OfferDraw { game_id } => {
+                buffer.put_u8(5); // Indicator for OfferDraw
+                buffer.put_u32(*game_id);
+            }
+            Transaction::AcceptDraw { game_id } => {
+                buffer.put_u8(6); // Indicator for AcceptDraw
+                buffer.put_u32(*game_id);
+            }
+            Transaction::DeclineDraw { game_id } => {
+                buffer.put_u8(7); // Indicator for DeclineDraw
+                buffer.put_u32(*game_id);
+            }
+            Transaction::ClaimDraw { game_id, claim } => {
+                buffer.put_u8(8); // Indicator for ClaimDraw
+                buffer.put_u32(*game_id);
+                buffer.put_u8(match claim {
+                    DrawClaim::Repetition => 0,
+                    DrawClaim::FiftyMoves => 1,
+                });
+            }
+            Transaction::Abort { game_id } => {
+                buffer.put_u8(9); // Indicator for Abort
+                buffer.put_u32(*game_id);
+            }
+            Transaction::Withdraw {
+                asset,
+                amount,
+                destination,
+                memo,
+            } => {
+                buffer.put_u8(10); // Indicator for Withdraw
+                put_string(&mut buffer, asset);
+                buffer.put_u128(*amount);
+                put_string(&mut buffer, destination);
+                put_string(&mut buffer, memo);
+            }
+        }
+
+        buffer.freeze()
+    }
+
+    // Decode bytes back into a transaction. Every transaction has a single encoding, so
+    // trailing bytes are rejected.
+    pub fn decode(mut data: Bytes) -> Result<Self, String> {
+        let transaction = Self::decode_fields(&mut data)?;
+        if data.has_remaining() {
+            return Err("Trailing bytes after transaction".into());
+        }
+        Ok(transaction)
+    }
+
+    fn decode_fields(data: &mut Bytes) -> Result<Self, String> {
+        if data.remaining() < 1 {
+            return Err("Data too short".into());
+        }
+
+        let tx_type = data.get_u8();
+        if tx_type == 3 {
+            let time_control = get_time_control(data)?;
+            let stake = get_stake(data)?;
+            return Ok(Transaction::CreateGame {
+                time_control,
+                stake,
+            });
+        }
+        if tx_type == 10 {
+            let asset = get_string(data, "asset")?;
+            if data.remaining() < 16 {
+                return Err("Data too short for amount".into());
+            }
+            let amount = data.get_u128();
+            let destination = get_string(data, "destination")?;
+            let memo = get_string(data, "memo")?;
+            return Ok(Transaction::Withdraw {
+                asset,
+                amount,
+                destination,
+                memo,
+            });
+        }
+
+        if data.remaining() < 4 {
+            return Err("Data too short for game id".into());
+        }
+        let game_id = data.get_u32();
+
+        match tx_type {
+            0 => {
+                let time_control = get_time_control(data)?;
+                let stake = get_stake(data)?;
+                Ok(Transaction::StartGame {
+                    game_id,
+                    time_control,
+                    stake,
+                })
+            }
+            1 => {
+                if data.remaining() < 4 {
+                    return Err("Data too short for move length".into());
+                }
+                let move_len = data.get_u32() as usize;
+
+                if data.remaining() < move_len {
+                    return Err("Data too short for move".into());
+                }
+
+                let move_san = String::from_utf8(data.split_to(move_len).to_vec())
+                    .map_err(|_| "Invalid UTF-8 in move string")?;
+
+                Ok(Transaction::MakeMove { game_id, move_san })
+            }
+            2 => Ok(Transaction::JoinGame { game_id }),
+            4 => Ok(Transaction::Resign { game_id }),
+            5 => Ok(Transaction::OfferDraw { game_id }),
+            6 => Ok(Transaction::AcceptDraw { game_id }),
+            7 => Ok(Transaction::DeclineDraw { game_id }),
+            8 => {
+                if data.remaining() < 1 {
+                    return Err("Data too short for draw claim".into());
+                }
+                let claim = match data.get_u8() {
+                    0 => DrawClaim::Repetition,
+                    1 => DrawClaim::FiftyMoves,
+                    _ => return Err("Unknown draw claim".into()),
+                };
+                Ok(Transaction::ClaimDraw { game_id, claim })
+            }
+            9 => Ok(Transaction::Abort { game_id }),
+            _ => Err("Unknown transaction type".into()),
+        }
+    }
+}
+
+impl SignedTransaction {
+    // Bytes covered by the signature: the rollup id, so signatures cannot be replayed on another
+    // rollup, then the nonce and the encoded transaction
+    pub fn signing_bytes(rollup_id: &[u8; 32], nonce: u64, transaction: &Transaction) -> Bytes {
+        let mut buffer = BytesMut::new();
+        buffer.put_slice(rollup_id);
+        buffer.put_u64(nonce);
+        buffer.put_slice(&transaction.encode());
+        buffer.freeze()
+    }
+
+    // Check the signature against the embedded public key
+    pub fn verify(&self, rollup_id: &[u8; 32]) -> Result<(), String> {
+        let verification_key =
+            VerificationKey::try_from(self.public_key).map_err(|_| "Invalid public key")?;
+        let signature = Signature::from(self.signature);
+        verification_key
+            .verify(
+                &signature,
+                &Self::signing_bytes(rollup_id, self.nonce, &self.transaction),
+            )
+            .map_err(|_| "Invalid signature".into())
+    }
+
+    // Encode as public key, signature and nonce followed by the transaction
+    pub fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::new();
+        buffer.put_slice(&self.public_key);
+        buffer.put_slice(&self.signature);
+        buffer.put_u64(self.nonce);
+        buffer.put_slice(&self.transaction.encode());
+        buffer.freeze()
+    }
+
+    // Decode bytes back into a signed transaction
+    pub fn decode(mut data: Bytes) -> Result<Self, String> {
+        if data.remaining() < 32 + 64 + 8 {
+            return Err("Data too short for signed transaction".into());
+        }
+
+        let mut public_key = [0u8; 32];
+        data.copy_to_slice(&mut public_key);
+        let mut signature = [0u8; 64];
+        data.copy_to_slice(&mut signature);
+        let nonce = data.get_u64();
+        // The signature covers the re-encoded transaction, which has to be the one received
+        let transaction = Transaction::decode(data.clone())?;
+        if transaction.encode() != data {
+            return Err("Non-canonical transaction encoding".into());
+        }
+
+        Ok(SignedTransaction {
+            public_key,
+            signature,
+            nonce,
+            transaction,
+        })
+    }
+}
+
+// Hash identifying a transaction: sha256 over its encoded bytes
+pub fn transaction_hash(encoded_tx: &[u8]) -> [u8; 32] {
+    Sha256::digest(encoded_tx).into()
+}
+
+// Strings are encoded as a u32 byte length followed by UTF-8 bytes
+fn put_string(buffer: &mut BytesMut, value: &str) {
+    buffer.put_u32(value.len() as u32);
+    buffer.put_slice(value.as_bytes());
+}
+
+fn get_string(data: &mut Bytes, field: &str) -> Result<String, String> {
+    if data.remaining() < 4 {
+        return Err(format!("Data too short for {} length", field));
+    }
+    let len = data.get_u32() as usize;
+    if data.remaining() < len {
+        return Err(format!("Data too short for {}", field));
+    }
+    String::from_utf8(data.split_to(len).to_vec())
+        .map_err(|_| format!("Invalid UTF-8 in {}", field))
+}
+
+// Game terms trail the game id: the time control, then a flag byte followed by the stake if
+// the game is wagered
+fn put_game_terms(
+    buffer: &mut BytesMut,
+    time_control: &Option<TimeControl>,
+    stake: &Option<Stake>,
+) {
+    put_time_control(buffer, time_control);
+    match stake {
+        None => buffer.put_u8(0),
+        Some(stake) => {
+            buffer.put_u8(1);
+            put_string(buffer, &stake.asset);
+            buffer.put_u128(stake.amount);
+        }
+    }
+}
+
+fn get_stake(data: &mut Bytes) -> Result<Option<Stake>, String> {
+    if !data.has_remaining() {
+        return Err("Data too short for stake".into());
+    }
+    match data.get_u8() {
+        0 => return Ok(None),
+        1 => {}
+        _ => return Err("Unknown stake flag".into()),
+    }
+    let asset = get_string(data, "stake asset")?;
+    if data.remaining() < 16 {
+        return Err("Data too short for stake amount".into());
+    }
+    let amount = data.get_u128();
+    if amount == 0 {
+        return Err("Stake amount must be positive".into());
+    }
+    Ok(Some(Stake { asset, amount }))
+}
+
+// Time controls are encoded as a kind byte followed by its parameters, kind 0 for untimed games
+fn put_time_control(buffer: &mut BytesMut, time_control: &Option<TimeControl>) {
+    match time_control {
+        None => buffer.put_u8(0),
+        Some(TimeControl::Clock {
+            initial_ms,
+            increment_ms,
+        }) => {
+            buffer.put_u8(1);
+            buffer.put_u64(*initial_ms);
+            buffer.put_u64(*increment_ms);
+        }
+        Some(TimeControl::Blocks { per_move }) => {
+            buffer.put_u8(2);
+            buffer.put_u32(*per_move);
+        }
+    }
+}
+
+fn get_time_control(data: &mut Bytes) -> Result<Option<TimeControl>, String> {
+    if !data.has_remaining() {
+        return Err("Data too short for time control".into());
+    }
+    match data.get_u8() {
+        0 => Ok(None),
+        1 => {
+            if data.remaining() < 16 {
+                return Err("Data too short for clock time control".into());
+            }
+            let initial_ms = data.get_u64();
+            let increment_ms = data.get_u64();
+            if initial_ms == 0 {
+                return Err("Clock time control needs a positive initial time".into());
+            }
+            Ok(Some(TimeControl::Clock {
+                initial_ms,
+                increment_ms,
+            }))
+        }
+        2 => {
+            if data.remaining() < 4 {
+                return Err("Data too short for block time control".into());
+            }
+            let per_move = data.get_u32();
+            if per_move == 0 {
+                return Err("Block time control needs at least one block per move".into());
+            }
+            Ok(Some(TimeControl::Blocks { per_move }))
+        }
+        _ => Err("Unknown time control".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astria_core::crypto::SigningKey;
+
+    const ROLLUP_ID: [u8; 32] = [7u8; 32];
+
+    fn stake() -> Option<Stake> {
+        Some(Stake {
+            asset: "nria".into(),
+            amount: 10_001,
+        })
+    }
+
+    fn every_transaction() -> Vec<Transaction> {
+        vec![
+            Transaction::StartGame {
+                game_id: 1,
+                time_control: None,
+                stake: None,
+            },
+            Transaction::StartGame {
+                game_id: 2,
+                time_control: Some(TimeControl::Clock {
+                    initial_ms: 60_000,
+                    increment_ms: 1_000,
+                }),
+                stake: stake(),
+            },
+            Transaction::MakeMove {
+                game_id: 1,
+                move_san: "e4".into(),
+            },
+            Transaction::JoinGame { game_id: 1 },
+            Transaction::CreateGame {
+                time_control: None,
+                stake: stake(),
+            },
+            Transaction::CreateGame {
+                time_control: Some(TimeControl::Blocks { per_move: 5 }),
+                stake: None,
+            },
+            Transaction::Resign { game_id: 1 },
+            Transaction::OfferDraw { game_id: 1 },
+            Transaction::AcceptDraw { game_id: 1 },
+            Transaction::DeclineDraw { game_id: 1 },
+            Transaction::ClaimDraw {
+                game_id: 1,
+                claim: DrawClaim::Repetition,
+            },
+            Transaction::ClaimDraw {
+                game_id: 1,
+                claim: DrawClaim::FiftyMoves,
+            },
+            Transaction::Abort { game_id: 1 },
+            Transaction::Withdraw {
+                asset: "nria".into(),
+                amount: u128::MAX,
+                destination: "astria1destination".into(),
+                memo: "memo".into(),
+            },
+        ]
+    }
+
+    fn sign(transaction: Transaction, nonce: u64) -> SignedTransaction {
+        let signing_key = SigningKey::from([1u8; 32]);
+        let signature = signing_key.sign(&SignedTransaction::signing_bytes(
+            &ROLLUP_ID,
+            nonce,
+            &transaction,
+        ));
+        SignedTransaction {
+            public_key: signing_key.verification_key().to_bytes(),
+            signature: signature.to_bytes(),
+            nonce,
+            transaction,
+        }
+    }
+
+    #[test]
+    fn every_transaction_round_trips() {
+        for transaction in every_transaction() {
+            let decoded = Transaction::decode(transaction.encode()).unwrap();
+            assert_eq!(decoded, transaction);
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        for transaction in every_transaction() {
+            let mut padded = BytesMut::from(&transaction.encode()[..]);
+            padded.put_u8(0);
+            assert!(
+                Transaction::decode(padded.freeze()).is_err(),
+                "{:?}",
+                transaction
+            );
+        }
+    }
+
+    #[test]
+    fn game_terms_have_a_single_encoding() {
+        let untimed = Transaction::CreateGame {
+            time_control: None,
+            stake: None,
+        };
+        assert_eq!(&untimed.encode()[..], &[3, 0, 0]);
+        // Terms cannot be left out
+        assert!(Transaction::decode(Bytes::from_static(&[3])).is_err());
+        assert!(Transaction::decode(Bytes::from_static(&[3, 0])).is_err());
+        assert!(Transaction::decode(Bytes::from_static(&[3, 0, 2])).is_err());
+    }
+
+    #[test]
+    fn signed_transactions_round_trip_and_verify() {
+        for (nonce, transaction) in every_transaction().into_iter().enumerate() {
+            let signed = sign(transaction, nonce as u64);
+            let decoded = SignedTransaction::decode(signed.encode()).unwrap();
+            assert_eq!(decoded.transaction, signed.transaction);
+            assert_eq!(decoded.nonce, signed.nonce);
+            decoded.verify(&ROLLUP_ID).unwrap();
+        }
+    }
+
+    #[test]
+    fn padded_signed_transactions_are_rejected() {
+        let signed = sign(Transaction::JoinGame { game_id: 1 }, 0);
+        let mut padded = BytesMut::from(&signed.encode()[..]);
+        padded.put_u8(0);
+        assert!(SignedTransaction::decode(padded.freeze()).is_err());
+    }
+
+    #[test]
+    fn signatures_are_bound_to_the_rollup() {
+        let signed = sign(Transaction::JoinGame { game_id: 1 }, 0);
+        assert!(signed.verify(&[8u8; 32]).is_err());
+        let mut other_nonce = signed.clone();
+        other_nonce.nonce = 1;
+        assert!(other_nonce.verify(&ROLLUP_ID).is_err());
+    }
+}